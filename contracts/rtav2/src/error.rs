@@ -0,0 +1,154 @@
+/*!
+Typed errors for the RTA factory.
+
+Every panic in the contract goes through [`RtaError`], so clients and tests can
+match on the stable `Display` strings below.
+*/
+
+use near_sdk::{FunctionError, NearToken};
+use std::fmt;
+
+/// `require!` for [`RtaError`]: panics with `$error` unless `$cond` holds. The error is
+/// only constructed on failure.
+macro_rules! require_rta {
+    ($cond:expr, $error:expr $(,)?) => {
+        if !$cond {
+            near_sdk::FunctionError::panic(&$error)
+        }
+    };
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, FunctionError)]
+pub enum RtaError {
+    AlreadyInitialized,
+    NotInitialized,
+    NotFound,
+    AlreadyExists,
+    InvalidRtaId(String),
+    MetadataMissing,
+    InvalidMetadata,
+    Unauthorized,
+    NotOwner,
+    NotContractOwner,
+    NoDelegation,
+    AlreadyClosed,
+    NotClosed,
+    ReopenWindowExpired,
+    AlreadyLive,
+    NotLive,
+    CurrentlyLive,
+    Expired,
+    AlreadyOwner,
+    ReceiverNotCaller,
+    ChunkCountMismatch { expected: u32, actual: u32 },
+    TooManyChunks(u32),
+    InsufficientDeposit(NearToken),
+    IncorrectPayment(u128),
+    InsufficientBalance,
+    NotRegistered,
+    InsufficientStorage(NearToken),
+    StorageInUse,
+    InvalidMode(String),
+    InvalidDistance(u32),
+    MissingTicketAmount,
+    InvalidTicketPrice,
+    InvalidStreamPrice,
+    RoyaltiesTooHigh(u32),
+    TooManyCollaborators(usize),
+    FeeTooHigh(u16),
+    NotGroupMode,
+    AlreadyGroupMode,
+    NotPayPerStream,
+    SoldOut,
+    TicketsSold(u32),
+    HasPayments,
+    OutOfRange,
+    InvalidLimit,
+    BatchTooLarge(usize),
+    InvalidMediaHash,
+    InvalidContentHash,
+    MediaHashWithoutMedia,
+    TooManyPayoutReceivers,
+    NonTransferable(&'static str),
+}
+
+impl fmt::Display for RtaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::AlreadyInitialized => write!(f, "Already initialized"),
+            Self::NotInitialized => write!(f, "Contract state not found"),
+            Self::NotFound => write!(f, "RTA not found"),
+            Self::AlreadyExists => write!(f, "RTA already exists"),
+            Self::InvalidRtaId(rta_id) => write!(
+                f,
+                "Invalid rta_id '{}': use 1-{} of [A-Za-z0-9_-] without the \"rta_\" prefix",
+                rta_id,
+                crate::MAX_RTA_ID_LEN
+            ),
+            Self::MetadataMissing => write!(f, "RTA metadata missing"),
+            Self::InvalidMetadata => write!(f, "RTA extra is not valid JSON"),
+            Self::Unauthorized => write!(f, "Not authorized"),
+            Self::NotOwner => write!(f, "Only the RTA owner can call this method"),
+            Self::NotContractOwner => write!(f, "Only the contract owner can call this method"),
+            Self::NoDelegation => write!(f, "No delegation"),
+            Self::AlreadyClosed => write!(f, "RTA is already closed"),
+            Self::NotClosed => write!(f, "RTA is not closed"),
+            Self::ReopenWindowExpired => write!(f, "RTA can no longer be reopened"),
+            Self::AlreadyLive => write!(f, "RTA is already live"),
+            Self::NotLive => write!(f, "RTA is not live"),
+            Self::CurrentlyLive => write!(f, "RTA is live; end the stream first"),
+            Self::Expired => write!(f, "RTA has expired"),
+            Self::AlreadyOwner => write!(f, "RTA is already owned by this account"),
+            Self::ReceiverNotCaller => write!(f, "receiver_id must be the caller to set a delegate"),
+            Self::ChunkCountMismatch { expected, actual } => {
+                write!(f, "Chunk count mismatch: expected {} chunks, recorded {}", expected, actual)
+            }
+            Self::TooManyChunks(max) => write!(f, "Too many chunks: an RTA can record at most {}", max),
+            Self::InsufficientDeposit(required) => {
+                write!(f, "Insufficient deposit: must attach at least {}", required.exact_amount_display())
+            }
+            Self::IncorrectPayment(price) => {
+                write!(f, "Incorrect payment: attached deposit must equal {} yoctoNEAR", price)
+            }
+            Self::InsufficientBalance => write!(f, "Insufficient balance above the storage staking floor"),
+            Self::NotRegistered => write!(f, "Account is not registered; call storage_deposit first"),
+            Self::InsufficientStorage(required) => {
+                write!(f, "Insufficient storage balance: {} required", required.exact_amount_display())
+            }
+            Self::StorageInUse => write!(f, "Account has storage in use; pass force to unregister anyway"),
+            Self::InvalidMode(mode) => write!(f, "Invalid mode '{}': expected \"solo\" or \"group\"", mode),
+            Self::InvalidDistance(distance) => {
+                write!(f, "Invalid distance {}: must be at most {} meters", distance, crate::MAX_DISTANCE_METERS)
+            }
+            Self::MissingTicketAmount => write!(f, "Group mode requires a non-zero ticket_amount"),
+            Self::InvalidTicketPrice => write!(f, "Group mode requires ticket_price as a yoctoNEAR amount"),
+            Self::InvalidStreamPrice => write!(f, "Pay-per-stream requires stream_price as a yoctoNEAR amount"),
+            Self::RoyaltiesTooHigh(total_bps) => write!(
+                f,
+                "Royalties total {} basis points; must be at most {}",
+                total_bps,
+                crate::MAX_ROYALTY_BPS
+            ),
+            Self::TooManyCollaborators(max) => write!(f, "Too many collaborators: at most {}", max),
+            Self::FeeTooHigh(fee_bps) => {
+                write!(f, "Protocol fee of {} basis points exceeds the maximum of {}", fee_bps, crate::MAX_FEE_BPS)
+            }
+            Self::NotGroupMode => write!(f, "RTA is not in group mode"),
+            Self::AlreadyGroupMode => write!(f, "RTA is already in group mode"),
+            Self::NotPayPerStream => write!(f, "RTA is not pay-per-stream"),
+            Self::SoldOut => write!(f, "Tickets sold out"),
+            Self::TicketsSold(sold) => {
+                write!(f, "{} tickets already sold: the RTA must stay in group mode with at least that many", sold)
+            }
+            Self::HasPayments => write!(f, "RTA has ticket holders or stream payments and cannot be burned"),
+            Self::OutOfRange => write!(f, "Out of range"),
+            Self::InvalidLimit => write!(f, "Cannot provide limit of 0."),
+            Self::BatchTooLarge(max) => write!(f, "Batch too large: at most {} items", max),
+            Self::InvalidMediaHash => write!(f, "media_hash must be base64 of a 32-byte hash"),
+            Self::InvalidContentHash => write!(f, "content_sha256 must be 64 hex characters"),
+            Self::MediaHashWithoutMedia => write!(f, "media_hash requires media"),
+            Self::TooManyPayoutReceivers => write!(f, "Market cannot payout to that many receivers"),
+            Self::NonTransferable(method) => write!(f, "Non-transferable NFT: {} is disabled", method),
+        }
+    }
+}
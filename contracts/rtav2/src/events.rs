@@ -0,0 +1,92 @@
+/*!
+NEP-297 events for the vibestream lifecycle.
+
+`nft_mint` is emitted through the NEP-171 core events, with `{"rta_id": ...}` as
+its memo; the events here cover the RTA-specific transitions under the
+`vibesflow_rta` standard.
+*/
+
+use near_sdk::json_types::U128;
+use near_sdk::serde::Serialize;
+use near_sdk::{env, AccountIdRef};
+
+const RTA_STANDARD_NAME: &str = "vibesflow_rta";
+const RTA_STANDARD_VERSION: &str = "1.0.0";
+
+/// Emitted when a vibestream starts streaming.
+#[must_use]
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RtaLive<'a> {
+    pub rta_id: &'a str,
+}
+
+impl RtaLive<'_> {
+    pub fn emit(self) {
+        RtaEventKind::RtaLive(&[self]).emit()
+    }
+}
+
+/// Emitted when a vibestream is closed with its Filecoin master CID.
+#[must_use]
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RtaFinalize<'a> {
+    pub rta_id: &'a str,
+    pub filecoin_master_cid: &'a str,
+    pub total_chunks: u32,
+}
+
+impl RtaFinalize<'_> {
+    pub fn emit(self) {
+        RtaEventKind::RtaFinalize(&[self]).emit()
+    }
+}
+
+/// Emitted when a listener pays for a pay-per-stream vibestream.
+#[must_use]
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RtaStreamPayment<'a> {
+    pub rta_id: &'a str,
+    pub payer_id: &'a AccountIdRef,
+    pub amount: U128,
+}
+
+impl RtaStreamPayment<'_> {
+    pub fn emit(self) {
+        RtaEventKind::RtaStreamPayment(&[self]).emit()
+    }
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+#[serde(tag = "event", content = "data")]
+#[serde(rename_all = "snake_case")]
+#[allow(clippy::enum_variant_names)]
+enum RtaEventKind<'a> {
+    RtaLive(&'a [RtaLive<'a>]),
+    RtaFinalize(&'a [RtaFinalize<'a>]),
+    RtaStreamPayment(&'a [RtaStreamPayment<'a>]),
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+struct RtaEvent<'a> {
+    standard: &'static str,
+    version: &'static str,
+    #[serde(flatten)]
+    event_kind: RtaEventKind<'a>,
+}
+
+impl RtaEventKind<'_> {
+    fn emit(self) {
+        let event = RtaEvent {
+            standard: RTA_STANDARD_NAME,
+            version: RTA_STANDARD_VERSION,
+            event_kind: self,
+        };
+        let json = serde_json::to_string(&event).unwrap_or_else(|_| env::abort());
+        env::log_str(&format!("EVENT_JSON:{}", json));
+    }
+}
@@ -14,18 +14,32 @@ use near_contract_standards::non_fungible_token::core::NonFungibleTokenCore;
 use near_contract_standards::non_fungible_token::NonFungibleTokenResolver;
 use near_contract_standards::non_fungible_token::approval::NonFungibleTokenApproval;
 use near_contract_standards::non_fungible_token::enumeration::NonFungibleTokenEnumeration;
+use near_contract_standards::non_fungible_token::events::{NftBurn, NftMint, NftTransfer};
+use near_contract_standards::storage_management::{StorageBalance, StorageBalanceBounds, StorageManagement};
 
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
 use near_sdk::collections::LazyOption;
-use near_sdk::json_types::U128;
+use base64::prelude::{Engine, BASE64_STANDARD};
+use near_sdk::json_types::{Base64VecU8, U128};
 use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::store::{IterableSet, LookupMap};
 use near_sdk::{
-    env, near_bindgen, require, AccountId, BorshStorageKey, NearToken, PanicOnDefault, Promise,
-    PromiseOrValue,
+    assert_one_yocto, env, near_bindgen, AccountId, BorshStorageKey, FunctionError, NearToken, PanicOnDefault,
+    Promise, PromiseOrValue,
 };
 use schemars::JsonSchema;
 use std::collections::HashMap;
 
+#[macro_use]
+mod error;
+pub mod events;
+mod migrate;
+#[cfg(test)]
+mod tests;
+
+pub use error::RtaError;
+use events::{RtaFinalize, RtaLive, RtaStreamPayment};
+
 #[derive(BorshSerialize, BorshStorageKey)]
 enum StorageKey {
     NonFungibleToken,
@@ -33,6 +47,8 @@ enum StorageKey {
     TokenMetadata,
     Enumeration,
     Approval,
+    LiveRtas,
+    StorageBalances,
 }
 
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, JsonSchema)]
@@ -40,13 +56,125 @@ enum StorageKey {
 pub struct RTAConfig {
     pub mode: String, // "solo" or "group"
     pub store_to_filecoin: bool,
-    pub distance: Option<u32>, // meters, up to 10
+    pub distance: Option<u32>, // meters, up to MAX_DISTANCE_METERS
     pub ticket_amount: Option<u32>,
     pub ticket_price: Option<String>,
     pub pay_per_stream: bool,
     pub stream_price: Option<String>,
     pub creator: String,
     pub created_at: u64,
+    /// Royalty splits in basis points, summing to at most 10000
+    #[schemars(with = "Option<HashMap<String, u16>>")]
+    pub royalties: Option<HashMap<AccountId, u16>>,
+    /// Cover art URL copied into the NFT's `TokenMetadata.media`
+    pub media: Option<String>,
+    /// Base64 sha256 of the `media` content (32 bytes decoded)
+    pub media_hash: Option<String>,
+    /// Co-producers who may add chunks without a delegation; finalizing still needs one
+    #[serde(default)]
+    #[schemars(with = "Vec<String>")]
+    pub collaborators: Vec<AccountId>,
+    /// Chunks the stream is expected to produce, used to size the minimum deposit;
+    /// `DEFAULT_EXPECTED_CHUNKS` when unset
+    pub expected_chunks: Option<u32>,
+    /// Anchor the token's `extra` JSON with its sha256 in `TokenMetadata.reference_hash`,
+    /// recomputed whenever the RTA is saved
+    #[serde(default)]
+    pub hash_extra: bool,
+    /// Block timestamp (ns) after which the RTA counts as expired and can no longer go live;
+    /// copied into `TokenMetadata.expires_at`
+    pub expires_at: Option<u64>,
+}
+
+impl RTAConfig {
+    pub fn validate(&self) {
+        require_rta!(self.mode == "solo" || self.mode == "group", RtaError::InvalidMode(self.mode.clone()));
+        if let Some(distance) = self.distance {
+            require_rta!(distance <= MAX_DISTANCE_METERS, RtaError::InvalidDistance(distance));
+        }
+        if self.mode == "group" {
+            require_rta!(self.ticket_amount.unwrap_or(0) > 0, RtaError::MissingTicketAmount);
+            require_rta!(parse_yocto(self.ticket_price.as_deref()).is_some(), RtaError::InvalidTicketPrice);
+        }
+        if self.pay_per_stream {
+            require_rta!(parse_yocto(self.stream_price.as_deref()).is_some(), RtaError::InvalidStreamPrice);
+        }
+        if let Some(royalties) = &self.royalties {
+            let total_bps: u32 = royalties.values().map(|bps| *bps as u32).sum();
+            require_rta!(total_bps <= MAX_ROYALTY_BPS, RtaError::RoyaltiesTooHigh(total_bps));
+        }
+        require_rta!(
+            self.collaborators.len() <= MAX_COLLABORATORS,
+            RtaError::TooManyCollaborators(MAX_COLLABORATORS)
+        );
+        if self.media_hash.is_some() {
+            require_rta!(self.media.is_some(), RtaError::MediaHashWithoutMedia);
+            require_rta!(self.media_hash_bytes().is_some_and(|hash| hash.len() == 32), RtaError::InvalidMediaHash);
+        }
+    }
+
+    fn is_expired(&self) -> bool {
+        self.expires_at.is_some_and(|expires_at| env::block_timestamp() > expires_at)
+    }
+
+    fn media_hash_bytes(&self) -> Option<Vec<u8>> {
+        BASE64_STANDARD.decode(self.media_hash.as_ref()?).ok()
+    }
+
+    fn description(&self, rta_id: &str) -> String {
+        format!(
+            "Real-Time Asset for RTA #{} in {} mode{}{}",
+            rta_id,
+            self.mode,
+            if self.store_to_filecoin { ", stored on Filecoin" } else { "" },
+            if self.mode == "group" {
+                format!(", max {} participants", self.ticket_amount.unwrap_or(0))
+            } else { "".to_string() }
+        )
+    }
+}
+
+/// NEP-199 payout: how much of a balance each account receives.
+#[derive(Serialize, Deserialize, Default)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Payout {
+    pub payout: HashMap<AccountId, U128>,
+}
+
+/// `bps` basis points of `amount`, rounded down.
+fn bps_share(bps: u16, amount: u128) -> u128 {
+    let bps = bps as u128;
+    // Split the multiplication so large amounts can't overflow
+    amount / BPS_DENOMINATOR * bps + amount % BPS_DENOMINATOR * bps / BPS_DENOMINATOR
+}
+
+/// Borsh size of one more account in a per-RTA `Vec<AccountId>`, plus the map
+/// key and `Vec` header when the list doesn't exist yet.
+fn account_list_entry_bytes(rta_id: &str, account_id: &AccountId, new_list: bool) -> u64 {
+    let entry = 4 + account_id.len() as u64;
+    if new_list {
+        entry + 4 + rta_id.len() as u64 + 4
+    } else {
+        entry
+    }
+}
+
+/// Whether `rta_id` can be minted: non-empty, at most `MAX_RTA_ID_LEN` characters of
+/// `[A-Za-z0-9_-]`, and not already carrying the `rta_` token prefix.
+pub fn is_valid_rta_id(rta_id: &str) -> bool {
+    !rta_id.is_empty()
+        && rta_id.len() <= MAX_RTA_ID_LEN
+        && !rta_id.starts_with("rta_")
+        && rta_id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// sha256 of an RTA's serialized `extra`, as stored in `reference_hash`.
+fn extra_hash(extra: &str) -> Base64VecU8 {
+    env::sha256(extra.as_bytes()).into()
+}
+
+fn parse_yocto(amount: Option<&str>) -> Option<u128> {
+    amount?.parse().ok()
 }
 
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, JsonSchema)]
@@ -60,13 +188,167 @@ pub struct RTAMetadata {
     pub chunk_ownership: HashMap<u32, String>,
     pub total_chunks: u32,
     pub filecoin_master_cid: Option<String>,
+    /// Per-chunk size and duration, indexed like `chunk_cids`
+    #[serde(default)]
+    pub chunk_meta: Vec<ChunkMeta>,
+    /// Block height at which the RTA was minted
+    #[serde(default)]
+    pub created_block: u64,
+    /// Block height at which the RTA was finalized
+    #[serde(default)]
+    pub finalized_block: Option<u64>,
+    /// Block timestamp (ns) of the most recent `set_live`
+    #[serde(default)]
+    pub started_at: Option<u64>,
+    /// Block timestamp (ns) at which the most recent stream ended
+    #[serde(default)]
+    pub ended_at: Option<u64>,
+    /// Block timestamp (ns) of the finalize that closed the RTA
+    #[serde(default)]
+    pub finalized_at: Option<u64>,
+    /// Layout version of this metadata; tokens minted before versioning read as 1
+    #[serde(default = "RTAMetadata::legacy_schema_version")]
+    pub schema_version: u8,
+    /// Number of times the RTA has gone live
+    #[serde(default)]
+    pub sessions: u32,
+}
+
+impl RTAMetadata {
+    fn legacy_schema_version() -> u8 {
+        1
+    }
+
+    /// Parses a token's `extra`, upgrading metadata written under an older schema.
+    fn from_extra(extra: &str) -> serde_json::Result<Self> {
+        let mut rta_metadata: Self = serde_json::from_str(extra)?;
+        rta_metadata.upgrade();
+        Ok(rta_metadata)
+    }
+
+    /// Fills in what older schema versions lack and stamps `RTA_SCHEMA_VERSION`.
+    fn upgrade(&mut self) {
+        if self.schema_version < 2 {
+            // Version 1 tokens may have chunks recorded before `chunk_meta` existed
+            self.chunk_meta.resize(self.chunk_cids.len(), ChunkMeta::default());
+        }
+        self.schema_version = self.schema_version.max(RTA_SCHEMA_VERSION);
+    }
+
+    /// Stream session a payment made now pays for: the one currently live, or else the next.
+    fn current_session(&self) -> u32 {
+        if self.is_live { self.sessions } else { self.sessions + 1 }
+    }
+
+    /// Appends a chunk unless its CID is already recorded; returns whether it was added.
+    fn push_chunk(&mut self, chunk: ChunkInfo) -> bool {
+        // Retried dispatches resubmit CIDs that are already recorded
+        if self.chunk_cids.contains(&chunk.cid) {
+            return false;
+        }
+        // Ownership is keyed by the chunk's position in `chunk_cids`
+        let chunk_index = self.chunk_cids.len() as u32;
+        self.chunk_cids.push(chunk.cid);
+        if let Some(owner) = chunk.owner {
+            self.chunk_ownership.insert(chunk_index, owner.to_string());
+        }
+        self.chunk_meta.push(ChunkMeta {
+            size_bytes: chunk.size_bytes,
+            duration_ms: chunk.duration_ms,
+            content_sha256: chunk.content_sha256.map(|hash| hash.to_ascii_lowercase()),
+        });
+        self.total_chunks += 1;
+        true
+    }
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Default, JsonSchema)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ChunkMeta {
+    pub size_bytes: u64,
+    pub duration_ms: u32,
+    /// Lowercase hex sha256 of the chunk bytes as dispatched, checked by `verify_chunk`.
+    #[serde(default)]
+    pub content_sha256: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ChunkInfo {
+    pub cid: String,
+    pub owner: Option<AccountId>,
+    pub size_bytes: u64,
+    pub duration_ms: u32,
+    /// Hex sha256 of the chunk bytes; optional so callers without it keep working.
+    #[serde(default)]
+    pub content_sha256: Option<String>,
+}
+
+impl ChunkInfo {
+    fn has_valid_content_hash(&self) -> bool {
+        self.content_sha256.as_ref().is_none_or(|hash| hash.len() == 64 && hash.bytes().all(|b| b.is_ascii_hexdigit()))
+    }
 }
 
-#[derive(BorshDeserialize, BorshSerialize)]
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
 pub struct Delegation {
     pub delegate: AccountId,
     pub can_update: bool,
     pub can_finalize: bool,
+    /// Block timestamp (ns) after which the delegation lapses; `None` never expires
+    pub expires_at: Option<u64>,
+}
+
+impl Delegation {
+    fn is_expired(&self) -> bool {
+        self.expires_at.is_some_and(|expires_at| env::block_timestamp() > expires_at)
+    }
+
+    fn to_view(&self, rta_id: &str) -> DelegationView {
+        DelegationView {
+            rta_id: rta_id.to_string(),
+            delegate: self.delegate.clone(),
+            can_update: self.can_update,
+            can_finalize: self.can_finalize,
+            expires_at: self.expires_at,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct DelegationView {
+    pub rta_id: String,
+    pub delegate: AccountId,
+    pub can_update: bool,
+    pub can_finalize: bool,
+    pub expires_at: Option<u64>,
+}
+
+/// A `pay_for_stream` payment, tied to the session it pays for.
+#[derive(BorshDeserialize, BorshSerialize, Clone)]
+pub struct StreamPayment {
+    pub payer_id: AccountId,
+    pub paid_at: u64,
+    pub session: u32,
+}
+
+/// Protocol fee charged on top of the deposit `create_rta` keeps.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FeeConfig {
+    pub fee_bps: u16,
+    pub fee_recipient: AccountId,
+}
+
+/// Counts of minted, currently live, and finalized RTAs.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RtaCounts {
+    pub total: u64,
+    pub live: u64,
+    pub closed: u64,
 }
 
 #[near_bindgen]
@@ -74,9 +356,57 @@ pub struct Delegation {
 pub struct RTAv2 {
     tokens: NonFungibleToken,
     metadata: LazyOption<NFTContractMetadata>,
-    delegations: std::collections::HashMap<String, Delegation>,
+    delegations: std::collections::HashMap<String, Vec<Delegation>>,
+    ticket_holders: HashMap<String, Vec<AccountId>>,
+    participants: HashMap<String, Vec<AccountId>>,
+    live_rtas: IterableSet<String>,
+    stream_payments: HashMap<String, Vec<StreamPayment>>,
+    /// NEP-145 balances that pay for ticket holder and participant entries
+    storage_balances: LookupMap<AccountId, StorageBalance>,
+    /// What unregistering every `storage_balances` account would refund: each one's available
+    /// balance plus its registration minimum. Reserved from `available_balance`
+    storage_owed: NearToken,
+    /// Finalized RTAs that have not been burned or reopened
+    closed_rtas: u64,
+    /// Protocol fee in basis points of the deposit `create_rta` keeps, at most `MAX_FEE_BPS`
+    fee_bps: u16,
+    fee_recipient: AccountId,
+    /// Cap on chunks recorded per RTA, keeping a token's `extra` within storage and gas limits
+    max_chunks_per_rta: u32,
 }
 
+pub(crate) const MAX_DISTANCE_METERS: u32 = 10;
+/// `RTAMetadata` layout that `create_rta` writes and older tokens are upgraded to on read
+pub const RTA_SCHEMA_VERSION: u8 = 2;
+const MAX_CHUNK_PAGE_SIZE: u32 = 100;
+pub(crate) const MAX_ROYALTY_BPS: u32 = 10_000;
+const BPS_DENOMINATOR: u128 = 10_000;
+pub(crate) const MAX_FEE_BPS: u16 = 1_000;
+const DEFAULT_SCAN_LIMIT: u64 = 50;
+const MAX_BATCH_SIZE: usize = 50;
+/// Each finalize rewrites the RTA's full metadata, so batches stay smaller than view batches
+const MAX_FINALIZE_BATCH_SIZE: usize = 20;
+const MAX_COLLABORATORS: usize = 10;
+const MAX_SCAN_LIMIT: u64 = 100;
+/// Upper bound on the bytes a `storage_balances` record occupies, including trie overhead
+const STORAGE_BALANCE_RECORD_BYTES: u64 = 150;
+/// Longest `rta_id` `create_rta` accepts, and so the longest whose mint `quote_minimum_deposit` covers
+pub(crate) const MAX_RTA_ID_LEN: usize = 64;
+const MAX_ACCOUNT_ID_LEN: usize = 64;
+const BASE_DEPOSIT: NearToken = NearToken::from_millinear(10);
+const FILECOIN_DEPOSIT: NearToken = NearToken::from_millinear(5);
+/// Storage for one ticket holder entry: a length-prefixed account ID of maximum length
+const SEAT_STORAGE_BYTES: u64 = 4 + MAX_ACCOUNT_ID_LEN as u64;
+/// Storage one chunk adds to the RTA's `extra` JSON: CID, ownership entry and size/duration
+const CHUNK_STORAGE_BYTES: u64 = 160;
+const DEFAULT_EXPECTED_CHUNKS: u32 = 30;
+const DEFAULT_MAX_CHUNKS_PER_RTA: u32 = 5_000;
+/// Storage a mint writes besides the token metadata and ID keys: collection entries and
+/// per-record trie overhead (about 485 bytes measured)
+const MINT_OVERHEAD_BYTES: usize = 512;
+/// How long after `finalize` the owner may still `reopen` an RTA
+const REOPEN_GRACE_PERIOD_NS: u64 = 60 * 60 * 1_000_000_000;
+
 const DATA_IMAGE_SVG_NEAR_ICON: &str = "data:image/svg+xml,%3Csvg xmlns='http://www.w3.org/2000/svg' viewBox='0 0 288 288'%3E%3Cg id='l' data-name='l'%3E%3Cpath d='m187.58,79.81l-30.1,44.69a3.2,3.2,0,0,0,4.75,4.2L191.86,106a1.2,1.2,0,0,1,2,.91v80.46a1.2,1.2,0,0,1-2.12.77L102.18,77.93A15.35,15.35,0,0,0,90.47,72.5H87.34A15.34,15.34,0,0,0,72,87.84V201.16A15.34,15.34,0,0,0,87.34,216.5h0a15.35,15.35,0,0,0,13.08-7.31l30.1-44.69a3.2,3.2,0,0,0-4.75-4.2L96.14,182a1.2,1.2,0,0,1-2-.91V100.64a1.2,1.2,0,0,1,2.12-.77l89.55,109.21A15.35,15.35,0,0,0,197.53,215.5h3.13A15.34,15.34,0,0,0,216,200.16V86.84A15.34,15.34,0,0,0,200.66,71.5h0A15.35,15.35,0,0,0,187.58,79.81Z'/%3E%3C/g%3E%3C/svg%3E";
 
 #[near_bindgen]
@@ -99,18 +429,28 @@ impl RTAv2 {
 
     #[init]
     pub fn new(owner_id: AccountId, metadata: NFTContractMetadata) -> Self {
-        require!(!env::state_exists(), "Already initialized");
+        require_rta!(!env::state_exists(), RtaError::AlreadyInitialized);
         metadata.assert_valid();
         Self {
             tokens: NonFungibleToken::new(
                 StorageKey::NonFungibleToken,
-                owner_id,
+                owner_id.clone(),
                 Some(StorageKey::TokenMetadata),
                 Some(StorageKey::Enumeration),
                 Some(StorageKey::Approval),
             ),
             metadata: LazyOption::new(StorageKey::Metadata, Some(&metadata)),
             delegations: std::collections::HashMap::new(),
+            ticket_holders: HashMap::new(),
+            participants: HashMap::new(),
+            live_rtas: IterableSet::new(StorageKey::LiveRtas),
+            stream_payments: HashMap::new(),
+            storage_balances: LookupMap::new(StorageKey::StorageBalances),
+            storage_owed: NearToken::from_yoctonear(0),
+            closed_rtas: 0,
+            fee_bps: 0,
+            fee_recipient: owner_id,
+            max_chunks_per_rta: DEFAULT_MAX_CHUNKS_PER_RTA,
         }
     }
 
@@ -121,12 +461,72 @@ impl RTAv2 {
         config: RTAConfig,
         receiver_id: AccountId,
     ) -> Token {
+        config.validate();
+        require_rta!(is_valid_rta_id(&rta_id), RtaError::InvalidRtaId(rta_id));
+        let token_id = format!("rta_{}", rta_id);
+        require_rta!(!self.tokens.owner_by_id.contains_key(&token_id), RtaError::AlreadyExists);
         let deposit = env::attached_deposit();
+        let required_deposit = self.internal_required_deposit(&config);
+        require_rta!(deposit >= required_deposit, RtaError::InsufficientDeposit(required_deposit));
         let min_deposit = self.calculate_minimum_deposit(&config);
-        require!(deposit >= min_deposit, "Insufficient deposit for RTA creation");
-        let token_id = format!("rta_{}", rta_id);
+        let token_metadata = self.internal_new_token_metadata(&rta_id, &config);
+        let initial_storage_usage = env::storage_usage();
+        let token = self.tokens.internal_mint_with_refund(token_id, receiver_id, Some(token_metadata), None);
+        // The memo carries the rta_id so indexers and relayers that only see logs can map it
+        // to the token without a follow-up view call
+        let memo = serde_json::json!({ "rta_id": rta_id }).to_string();
+        NftMint { owner_id: &token.owner_id, token_ids: &[&token.token_id], memo: Some(&memo) }.emit();
+
+        // Keep the minimum deposit, but never less than the storage the mint just consumed. The
+        // fee is charged on what is kept, so overpaying doesn't raise it
+        let storage_cost = env::storage_byte_cost()
+            .saturating_mul((env::storage_usage() - initial_storage_usage).into());
+        let retained = std::cmp::max(min_deposit, storage_cost);
+        let fee = NearToken::from_yoctonear(bps_share(self.fee_bps, retained.as_yoctonear()));
+        require_rta!(
+            deposit.saturating_sub(fee) >= retained,
+            RtaError::InsufficientDeposit(retained.saturating_add(fee))
+        );
+        if !fee.is_zero() {
+            Promise::new(self.fee_recipient.clone()).transfer(fee);
+        }
+        let refund = deposit.saturating_sub(fee).saturating_sub(retained);
+        if !refund.is_zero() {
+            Promise::new(env::predecessor_account_id()).transfer(refund);
+        }
+        token
+    }
+
+    /// `create_rta` followed by `delegate_rta_permissions` in one transaction, so the RTA
+    /// never exists without its worker. The delegation does not expire. Only the token owner
+    /// picks its delegates, so `receiver_id` must be the caller.
+    #[payable]
+    pub fn create_rta_with_delegate(
+        &mut self,
+        rta_id: String,
+        config: RTAConfig,
+        receiver_id: AccountId,
+        delegate: AccountId,
+        permissions: Vec<String>,
+    ) -> Token {
+        require_rta!(receiver_id == env::predecessor_account_id(), RtaError::ReceiverNotCaller);
+        let token = self.create_rta(rta_id.clone(), config, receiver_id);
+        self.delegate_rta_permissions(rta_id, delegate, permissions, None);
+        token
+    }
+
+    /// Deposit in yoctoNEAR that `create_rta` requires for `config`: the minimum deposit, or the
+    /// estimated storage cost of the mint when that is higher, plus the protocol fee on it.
+    /// Any excess is refunded on mint.
+    pub fn quote_minimum_deposit(&self, config: RTAConfig) -> U128 {
+        config.validate();
+        U128(self.internal_required_deposit(&config).as_yoctonear())
+    }
+
+    /// Builds the token metadata, with the `RTAMetadata` as its `extra`, that `create_rta` mints.
+    fn internal_new_token_metadata(&self, rta_id: &str, config: &RTAConfig) -> TokenMetadata {
         let rta_metadata = RTAMetadata {
-            rta_id: rta_id.clone(),
+            rta_id: rta_id.to_string(),
             config: config.clone(),
             is_live: false,
             is_closed: false,
@@ -134,115 +534,827 @@ impl RTAv2 {
             chunk_ownership: HashMap::new(),
             total_chunks: 0,
             filecoin_master_cid: None,
+            chunk_meta: Vec::new(),
+            created_block: env::block_height(),
+            finalized_block: None,
+            started_at: None,
+            ended_at: None,
+            finalized_at: None,
+            schema_version: RTA_SCHEMA_VERSION,
+            sessions: 0,
         };
-        let token_metadata = TokenMetadata {
+        let extra = serde_json::to_string(&rta_metadata).unwrap();
+        TokenMetadata {
             title: Some(format!("VibesFlow RTA #{}", rta_id)),
-            description: Some(format!(
-                "Real-Time Asset for RTA #{} in {} mode{}{}",
-                rta_id,
-                config.mode,
-                if config.store_to_filecoin { ", stored on Filecoin" } else { "" },
-                if config.mode == "group" {
-                    format!(", max {} participants", config.ticket_amount.unwrap_or(0))
-                } else { "".to_string() }
-            )),
-            media: None,
-            media_hash: None,
+            description: Some(config.description(rta_id)),
+            media: config.media.clone(),
+            media_hash: config.media_hash_bytes().map(Base64VecU8::from),
             copies: Some(1),
             issued_at: Some(env::block_timestamp().to_string()),
-            expires_at: None,
+            expires_at: config.expires_at.map(|expires_at| expires_at.to_string()),
             starts_at: Some(config.created_at.to_string()),
             updated_at: Some(env::block_timestamp().to_string()),
-            extra: Some(serde_json::to_string(&rta_metadata).unwrap()),
+            reference_hash: config.hash_extra.then(|| extra_hash(&extra)),
+            extra: Some(extra),
             reference: None,
-            reference_hash: None,
-        };
-        self.tokens.internal_mint(token_id, receiver_id, Some(token_metadata))
+        }
+    }
+
+    /// `calculate_minimum_deposit`, raised to cover the mint's storage for an `rta_id` of up to
+    /// `MAX_RTA_ID_LEN` characters minted to an account ID of maximum length, plus the protocol
+    /// fee on that amount.
+    fn internal_required_deposit(&self, config: &RTAConfig) -> NearToken {
+        let rta_id = "x".repeat(MAX_RTA_ID_LEN);
+        let token_metadata = self.internal_new_token_metadata(&rta_id, config);
+        let metadata_bytes = borsh::to_vec(&token_metadata).unwrap_or_else(|_| env::abort()).len();
+        // The token ID lands in five keys/values across the NEP-171 collections and the owner ID in
+        // two; round both up to leave headroom
+        let key_bytes = 6 * ("rta_".len() + MAX_RTA_ID_LEN) + 3 * MAX_ACCOUNT_ID_LEN;
+        let mint_bytes = (metadata_bytes + key_bytes + MINT_OVERHEAD_BYTES) as u64;
+        let storage_cost = env::storage_byte_cost().saturating_mul(mint_bytes.into());
+        let required = std::cmp::max(self.calculate_minimum_deposit(config), storage_cost);
+        // `create_rta` keeps at most `required`, so its fee is at most the fee on `required`
+        required.saturating_add(NearToken::from_yoctonear(bps_share(self.fee_bps, required.as_yoctonear())))
     }
 
-    pub fn delegate_rta_permissions(&mut self, rta_id: String, delegate: AccountId, permissions: Vec<String>) {
+    /// Collects the deposit for moving an RTA from `old` to `new` config. `create_rta` kept at
+    /// least the minimum deposit for `old`, so the attached deposit must cover the rise in the
+    /// minimum deposit plus the protocol fee on it. The excess is refunded.
+    fn internal_charge_config_change(&self, old: &RTAConfig, new: &RTAConfig) {
+        let top_up = self.calculate_minimum_deposit(new).saturating_sub(self.calculate_minimum_deposit(old));
+        let fee = NearToken::from_yoctonear(bps_share(self.fee_bps, top_up.as_yoctonear()));
+        let deposit = env::attached_deposit();
+        require_rta!(
+            deposit >= top_up.saturating_add(fee),
+            RtaError::InsufficientDeposit(top_up.saturating_add(fee))
+        );
+        if !fee.is_zero() {
+            Promise::new(self.fee_recipient.clone()).transfer(fee);
+        }
+        let refund = deposit.saturating_sub(fee).saturating_sub(top_up);
+        if !refund.is_zero() {
+            Promise::new(env::predecessor_account_id()).transfer(refund);
+        }
+    }
+
+    /// Grants `delegate` the listed permissions (`update_chunks`, `finalize_rta`). Only the RTA
+    /// owner or the contract owner can delegate.
+    pub fn delegate_rta_permissions(
+        &mut self,
+        rta_id: String,
+        delegate: AccountId,
+        permissions: Vec<String>,
+        expires_at: Option<u64>,
+    ) {
+        let token_id = format!("rta_{}", rta_id);
+        let owner_id = self.tokens.owner_by_id.get(&token_id).unwrap_or_else(|| RtaError::NotFound.panic());
+        let caller = env::predecessor_account_id();
+        require_rta!(caller == owner_id || caller == self.tokens.owner_id, RtaError::NotOwner);
         let mut can_update = false;
         let mut can_finalize = false;
         for perm in permissions {
             if perm == "update_chunks" { can_update = true; }
             if perm == "finalize_rta" { can_finalize = true; }
         }
-        self.delegations.insert(rta_id, Delegation { delegate, can_update, can_finalize });
+        let delegations = self.delegations.entry(rta_id).or_default();
+        // Re-delegating to an existing delegate updates its permissions in place
+        match delegations.iter_mut().find(|d| d.delegate == delegate) {
+            Some(existing) => {
+                existing.can_update = can_update;
+                existing.can_finalize = can_finalize;
+                existing.expires_at = expires_at;
+            }
+            None => delegations.push(Delegation { delegate, can_update, can_finalize, expires_at }),
+        }
+    }
+
+    /// Revokes `delegate`, or every delegate of the RTA when `None`.
+    pub fn revoke_delegation(&mut self, rta_id: String, delegate: Option<AccountId>) {
+        let token_id = format!("rta_{}", rta_id);
+        self.assert_rta_owner(&token_id);
+        let delegations = self.delegations.get_mut(&rta_id).unwrap_or_else(|| RtaError::NoDelegation.panic());
+        match delegate {
+            Some(delegate) => {
+                let count = delegations.len();
+                delegations.retain(|d| d.delegate != delegate);
+                require_rta!(delegations.len() < count, RtaError::NoDelegation);
+                if delegations.is_empty() {
+                    self.delegations.remove(&rta_id);
+                }
+            }
+            None => {
+                self.delegations.remove(&rta_id);
+            }
+        }
+    }
+
+    pub fn get_delegation(&self, rta_id: String, delegate: AccountId) -> Option<DelegationView> {
+        self.internal_get_delegation(&rta_id, &delegate).map(|d| d.to_view(&rta_id))
+    }
+
+    pub fn get_delegations(&self, rta_id: String) -> Vec<DelegationView> {
+        self.delegations.get(&rta_id)
+            .map(|delegations| delegations.iter().map(|d| d.to_view(&rta_id)).collect())
+            .unwrap_or_default()
+    }
+
+    /// RTAs on which `delegate` currently holds an unexpired permission.
+    pub fn get_delegated_rtas(&self, delegate: AccountId) -> Vec<String> {
+        let mut rta_ids: Vec<String> = self.delegations
+            .iter()
+            .filter(|(_, delegations)| {
+                delegations.iter().any(|d| {
+                    d.delegate == delegate && !d.is_expired() && (d.can_update || d.can_finalize)
+                })
+            })
+            .map(|(rta_id, _)| rta_id.clone())
+            .collect();
+        // HashMap order is arbitrary; keep the result stable across calls
+        rta_ids.sort();
+        rta_ids
     }
 
     pub fn check_delegation(&self, rta_id: String, delegate: AccountId) -> bool {
-        self.delegations.get(&rta_id).map_or(false, |d| d.delegate == delegate && (d.can_update || d.can_finalize))
+        self.internal_get_delegation(&rta_id, &delegate).is_some_and(|d| d.can_update || d.can_finalize)
     }
 
-    pub fn add_cids(&mut self, rta_id: String, cids: Vec<String>, chunk_owners: Vec<AccountId>) {
+    /// Appends CIDs not already recorded for the RTA and returns how many were added.
+    /// Size and duration are recorded as zero; use `add_chunks` to supply them.
+    pub fn add_cids(&mut self, rta_id: String, cids: Vec<String>, chunk_owners: Vec<AccountId>) -> u32 {
+        let chunks = cids
+            .into_iter()
+            .enumerate()
+            .map(|(i, cid)| ChunkInfo {
+                cid,
+                owner: chunk_owners.get(i).cloned(),
+                size_bytes: 0,
+                duration_ms: 0,
+                content_sha256: None,
+            })
+            .collect();
+        self.add_chunks(rta_id, chunks)
+    }
+
+    /// Appends chunks not already recorded for the RTA and returns how many were added.
+    pub fn add_chunks(&mut self, rta_id: String, chunks: Vec<ChunkInfo>) -> u32 {
         let caller = env::predecessor_account_id();
-        let delegation = self.delegations.get(&rta_id).expect("No delegation");
-        require!(delegation.delegate == caller && delegation.can_update, "Not authorized");
-        let token_id = format!("rta_{}", rta_id);
-        let token = self.tokens.nft_token(token_id.clone()).expect("RTA not found");
-        if let Some(extra) = &token.metadata.as_ref().unwrap().extra {
-            let mut rta_metadata: RTAMetadata = serde_json::from_str(extra).unwrap();
-            require!(!rta_metadata.is_closed, "RTA is closed; cannot add more chunks");
-            for (i, cid) in cids.iter().enumerate() {
-                rta_metadata.chunk_cids.push(cid.clone());
-                if let Some(owner) = chunk_owners.get(i) {
-                    rta_metadata.chunk_ownership.insert(rta_metadata.total_chunks + 1, owner.to_string());
-                }
-                rta_metadata.total_chunks += 1;
+        let rta = self.internal_try_get_rta(&rta_id);
+        if let Err(error) = self.internal_check_add_chunks(&rta_id, rta.as_ref().map(|(_, _, rta_metadata)| rta_metadata), &caller) {
+            error.panic();
+        }
+        let Some((token_id, token_metadata, mut rta_metadata)) = rta else {
+            return 0;
+        };
+        require_rta!(chunks.iter().all(ChunkInfo::has_valid_content_hash), RtaError::InvalidContentHash);
+        require_rta!(
+            rta_metadata.total_chunks as u64 + chunks.len() as u64 <= self.max_chunks_per_rta as u64,
+            RtaError::TooManyChunks(self.max_chunks_per_rta)
+        );
+        let mut added = 0;
+        for chunk in chunks {
+            if rta_metadata.push_chunk(chunk) {
+                added += 1;
             }
-            let mut updated_metadata = token.metadata.unwrap();
-            updated_metadata.updated_at = Some(env::block_timestamp().to_string());
-            updated_metadata.extra = Some(serde_json::to_string(&rta_metadata).unwrap());
-            self.tokens.token_metadata_by_id.as_mut().unwrap().insert(&token_id, &updated_metadata);
         }
+        if added > 0 {
+            self.internal_save_rta(&token_id, token_metadata, &rta_metadata);
+        }
+        added
+    }
+
+    /// Whether `add_cids`/`add_chunks` from `account` would pass authorization for `rta_id`,
+    /// so workers can skip transactions that would fail. Chunk limits are not checked.
+    pub fn can_add_chunks(&self, rta_id: String, account: AccountId) -> bool {
+        if !self.tokens.owner_by_id.contains_key(&format!("rta_{}", rta_id)) {
+            return false;
+        }
+        let Some((_, _, rta_metadata)) = self.internal_try_get_rta(&rta_id) else {
+            return false;
+        };
+        self.internal_check_add_chunks(&rta_id, Some(&rta_metadata), &account).is_ok()
     }
 
     pub fn finalize(&mut self, rta_id: String, filecoin_master_cid: String) {
+        self.internal_finalize(rta_id, filecoin_master_cid, None);
+    }
+
+    /// Like `finalize`, but refuses to close the RTA unless exactly
+    /// `expected_total_chunks` chunks were recorded.
+    pub fn finalize_with_count(&mut self, rta_id: String, filecoin_master_cid: String, expected_total_chunks: u32) {
+        self.internal_finalize(rta_id, filecoin_master_cid, Some(expected_total_chunks));
+    }
+
+    /// Finalizes each `(rta_id, filecoin_master_cid)` pair the caller holds a `can_finalize`
+    /// delegation for, skipping the rest instead of aborting the batch. Returns whether each
+    /// RTA was finalized, in order.
+    pub fn finalize_batch(&mut self, finalizations: Vec<(String, String)>) -> Vec<(String, bool)> {
+        require_rta!(
+            finalizations.len() <= MAX_FINALIZE_BATCH_SIZE,
+            RtaError::BatchTooLarge(MAX_FINALIZE_BATCH_SIZE)
+        );
         let caller = env::predecessor_account_id();
-        let delegation = self.delegations.get(&rta_id).expect("No delegation");
-        require!(delegation.delegate == caller && delegation.can_finalize, "Not authorized");
-        let token_id = format!("rta_{}", rta_id);
-        let token = self.tokens.nft_token(token_id.clone()).expect("RTA not found");
-        if let Some(extra) = &token.metadata.as_ref().unwrap().extra {
-            let mut rta_metadata: RTAMetadata = serde_json::from_str(extra).unwrap();
-            require!(!rta_metadata.is_closed, "RTA is already closed");
-            rta_metadata.filecoin_master_cid = Some(filecoin_master_cid);
-            rta_metadata.is_closed = true;
-            let mut updated_metadata = token.metadata.unwrap();
-            updated_metadata.updated_at = Some(env::block_timestamp().to_string());
-            updated_metadata.extra = Some(serde_json::to_string(&rta_metadata).unwrap());
-            self.tokens.token_metadata_by_id.as_mut().unwrap().insert(&token_id, &updated_metadata);
+        finalizations
+            .into_iter()
+            .map(|(rta_id, filecoin_master_cid)| {
+                // Check up front everything `internal_finalize` would panic on
+                let finalized = self.internal_get_delegation(&rta_id, &caller).is_some_and(|d| d.can_finalize)
+                    && self.get_rta_metadata(rta_id.clone()).is_some_and(|rta_metadata| !rta_metadata.is_closed);
+                if finalized {
+                    self.internal_finalize(rta_id.clone(), filecoin_master_cid, None);
+                }
+                (rta_id, finalized)
+            })
+            .collect()
+    }
+
+    fn internal_finalize(&mut self, rta_id: String, filecoin_master_cid: String, expected_total_chunks: Option<u32>) {
+        let caller = env::predecessor_account_id();
+        let delegation = self.internal_get_delegation(&rta_id, &caller).unwrap_or_else(|| RtaError::NoDelegation.panic());
+        require_rta!(delegation.can_finalize, RtaError::Unauthorized);
+        let Some((token_id, token_metadata, mut rta_metadata)) = self.internal_try_get_rta(&rta_id) else {
+            return;
+        };
+        require_rta!(!rta_metadata.is_closed, RtaError::AlreadyClosed);
+        if let Some(expected) = expected_total_chunks {
+            require_rta!(
+                rta_metadata.total_chunks == expected,
+                RtaError::ChunkCountMismatch { expected, actual: rta_metadata.total_chunks }
+            );
+        }
+        rta_metadata.filecoin_master_cid = Some(filecoin_master_cid.clone());
+        rta_metadata.is_closed = true;
+        if rta_metadata.is_live {
+            rta_metadata.ended_at = Some(env::block_timestamp());
         }
+        rta_metadata.is_live = false;
+        rta_metadata.finalized_block = Some(env::block_height());
+        rta_metadata.finalized_at = Some(env::block_timestamp());
+        self.live_rtas.remove(&rta_id);
+        self.closed_rtas += 1;
+        self.internal_save_rta(&token_id, token_metadata, &rta_metadata);
+        RtaFinalize {
+            rta_id: &rta_id,
+            filecoin_master_cid: &filecoin_master_cid,
+            total_chunks: rta_metadata.total_chunks,
+        }
+        .emit();
     }
 
+    /// Undoes a mistaken `finalize` so chunks can be added again. Only the RTA owner can
+    /// reopen, and only within `REOPEN_GRACE_PERIOD_NS` of finalization.
+    pub fn reopen(&mut self, rta_id: String) {
+        let (token_id, token_metadata, mut rta_metadata) = self.internal_get_rta(&rta_id);
+        self.assert_rta_owner(&token_id);
+        require_rta!(rta_metadata.is_closed, RtaError::NotClosed);
+        let finalized_at = rta_metadata.finalized_at.unwrap_or_else(|| RtaError::ReopenWindowExpired.panic());
+        require_rta!(
+            env::block_timestamp().saturating_sub(finalized_at) <= REOPEN_GRACE_PERIOD_NS,
+            RtaError::ReopenWindowExpired
+        );
+        rta_metadata.is_closed = false;
+        rta_metadata.filecoin_master_cid = None;
+        rta_metadata.finalized_block = None;
+        rta_metadata.finalized_at = None;
+        self.closed_rtas -= 1;
+        self.internal_save_rta(&token_id, token_metadata, &rta_metadata);
+    }
+
+    pub fn set_live(&mut self, rta_id: String) {
+        let (token_id, token_metadata, mut rta_metadata) = self.internal_get_rta(&rta_id);
+        self.assert_owner_or_updater(&rta_id, &token_id);
+        require_rta!(!rta_metadata.is_closed, RtaError::AlreadyClosed);
+        require_rta!(!rta_metadata.is_live, RtaError::AlreadyLive);
+        require_rta!(!rta_metadata.config.is_expired(), RtaError::Expired);
+        rta_metadata.is_live = true;
+        rta_metadata.started_at = Some(env::block_timestamp());
+        rta_metadata.ended_at = None;
+        rta_metadata.sessions += 1;
+        self.live_rtas.insert(rta_id.clone());
+        self.internal_save_rta(&token_id, token_metadata, &rta_metadata);
+        RtaLive { rta_id: &rta_id }.emit();
+    }
+
+    pub fn end_stream(&mut self, rta_id: String) {
+        let (token_id, token_metadata, mut rta_metadata) = self.internal_get_rta(&rta_id);
+        self.assert_owner_or_updater(&rta_id, &token_id);
+        require_rta!(rta_metadata.is_live, RtaError::NotLive);
+        rta_metadata.is_live = false;
+        rta_metadata.ended_at = Some(env::block_timestamp());
+        self.live_rtas.remove(&rta_id);
+        self.internal_save_rta(&token_id, token_metadata, &rta_metadata);
+    }
+
+    /// Replaces the config of an RTA that has not gone live yet, keeping its
+    /// `creator` and `created_at`. A config that raises the minimum deposit needs the
+    /// difference attached, as in `upgrade_to_group`. Once tickets are sold the RTA must stay
+    /// in group mode with at least as many tickets as it has holders.
+    #[payable]
+    pub fn update_config(&mut self, rta_id: String, new_config: RTAConfig) {
+        let (token_id, mut token_metadata, mut rta_metadata) = self.internal_get_rta(&rta_id);
+        self.assert_rta_owner(&token_id);
+        require_rta!(!rta_metadata.is_closed, RtaError::AlreadyClosed);
+        require_rta!(!rta_metadata.is_live, RtaError::CurrentlyLive);
+
+        let config = RTAConfig {
+            creator: rta_metadata.config.creator.clone(),
+            created_at: rta_metadata.config.created_at,
+            ..new_config
+        };
+        config.validate();
+        let sold = self.ticket_holders.get(&rta_id).map_or(0, |holders| holders.len() as u32);
+        require_rta!(
+            sold == 0 || (config.mode == "group" && config.ticket_amount.unwrap_or(0) >= sold),
+            RtaError::TicketsSold(sold)
+        );
+        self.internal_charge_config_change(&rta_metadata.config, &config);
+        token_metadata.description = Some(config.description(&rta_id));
+        token_metadata.media = config.media.clone();
+        token_metadata.media_hash = config.media_hash_bytes().map(Base64VecU8::from);
+        token_metadata.expires_at = config.expires_at.map(|expires_at| expires_at.to_string());
+        rta_metadata.config = config;
+        self.internal_save_rta(&token_id, token_metadata, &rta_metadata);
+    }
+
+    /// Switches a solo RTA that has not gone live yet to group mode so it can sell tickets,
+    /// keeping its token ID. The seats raise the minimum deposit, so the caller attaches the
+    /// difference plus the protocol fee on it; any excess is refunded.
+    #[payable]
+    pub fn upgrade_to_group(&mut self, rta_id: String, ticket_amount: u32, ticket_price: String) {
+        let (token_id, mut token_metadata, mut rta_metadata) = self.internal_get_rta(&rta_id);
+        self.assert_rta_owner(&token_id);
+        require_rta!(!rta_metadata.is_closed, RtaError::AlreadyClosed);
+        require_rta!(!rta_metadata.is_live, RtaError::CurrentlyLive);
+        require_rta!(rta_metadata.config.mode != "group", RtaError::AlreadyGroupMode);
+
+        let old_config = rta_metadata.config.clone();
+        let config = &mut rta_metadata.config;
+        config.mode = "group".to_string();
+        config.ticket_amount = Some(ticket_amount);
+        config.ticket_price = Some(ticket_price);
+        config.validate();
+        token_metadata.description = Some(config.description(&rta_id));
+        self.internal_charge_config_change(&old_config, &rta_metadata.config);
+        self.internal_save_rta(&token_id, token_metadata, &rta_metadata);
+    }
+
+    /// Rewrites an RTA's `extra` in the current schema. Reads already upgrade older
+    /// metadata in memory; this persists it without waiting for the next update.
+    pub fn migrate_rta_metadata(&mut self, rta_id: String) {
+        require_rta!(env::predecessor_account_id() == self.tokens.owner_id, RtaError::NotContractOwner);
+        let (token_id, token_metadata, rta_metadata) = self.internal_get_rta(&rta_id);
+        self.internal_save_rta(&token_id, token_metadata, &rta_metadata);
+    }
+
+    /// Moves an RTA to a new owner account, e.g. when its creator rotates keys. Transfers
+    /// stay disabled for everyone else; only the current owner can reassign.
+    pub fn reassign_creator(&mut self, rta_id: String, new_owner: AccountId) {
+        let (token_id, token_metadata, mut rta_metadata) = self.internal_get_rta(&rta_id);
+        let owner_id = self.assert_rta_owner(&token_id);
+        require_rta!(new_owner != owner_id, RtaError::AlreadyOwner);
+
+        self.tokens.internal_transfer_unguarded(&token_id, &owner_id, &new_owner);
+        rta_metadata.config.creator = new_owner.to_string();
+        self.internal_save_rta(&token_id, token_metadata, &rta_metadata);
+
+        NftTransfer {
+            old_owner_id: &owner_id,
+            new_owner_id: &new_owner,
+            token_ids: &[&token_id],
+            authorized_id: None,
+            memo: Some("reassign_creator"),
+        }
+        .emit();
+    }
+
+    /// Burns a closed or never-live RTA and refunds the freed storage to its owner. RTAs that
+    /// sold tickets or took stream payments can't be burned, since those buyers would lose
+    /// what they paid for without a refund.
+    pub fn burn_rta(&mut self, rta_id: String) {
+        let (token_id, _, rta_metadata) = self.internal_get_rta(&rta_id);
+        let owner_id = self.assert_rta_owner(&token_id);
+        require_rta!(!rta_metadata.is_live, RtaError::CurrentlyLive);
+        // A stream that ended without being finalized still awaits its master CID
+        require_rta!(rta_metadata.is_closed || rta_metadata.started_at.is_none(), RtaError::NotClosed);
+        require_rta!(
+            self.ticket_holders.get(&rta_id).is_none_or(Vec::is_empty)
+                && self.stream_payments.get(&rta_id).is_none_or(Vec::is_empty),
+            RtaError::HasPayments
+        );
+
+        let initial_storage_usage = env::storage_usage();
+        self.tokens.owner_by_id.remove(&token_id);
+        if let Some(token_metadata_by_id) = &mut self.tokens.token_metadata_by_id {
+            token_metadata_by_id.remove(&token_id);
+        }
+        if let Some(tokens_per_owner) = &mut self.tokens.tokens_per_owner {
+            if let Some(mut token_ids) = tokens_per_owner.get(&owner_id) {
+                token_ids.remove(&token_id);
+                if token_ids.is_empty() {
+                    tokens_per_owner.remove(&owner_id);
+                } else {
+                    tokens_per_owner.insert(&owner_id, &token_ids);
+                }
+            }
+        }
+        if let Some(approvals_by_id) = &mut self.tokens.approvals_by_id {
+            approvals_by_id.remove(&token_id);
+        }
+        if let Some(next_approval_id_by_id) = &mut self.tokens.next_approval_id_by_id {
+            next_approval_id_by_id.remove(&token_id);
+        }
+        self.delegations.remove(&rta_id);
+        self.ticket_holders.remove(&rta_id);
+        self.participants.remove(&rta_id);
+        self.stream_payments.remove(&rta_id);
+        if rta_metadata.is_closed {
+            self.closed_rtas -= 1;
+        }
+
+        NftBurn { owner_id: &owner_id, token_ids: &[&token_id], authorized_id: None, memo: None }.emit();
+
+        let storage_freed = initial_storage_usage.saturating_sub(env::storage_usage());
+        let refund = env::storage_byte_cost().saturating_mul(storage_freed.into());
+        if !refund.is_zero() {
+            Promise::new(owner_id).transfer(refund);
+        }
+    }
+
+    /// Returns the full metadata including every chunk CID, which can exceed view gas
+    /// limits on long-running streams; prefer `get_chunk_cids` for the chunk list.
     pub fn get_rta_metadata(&self, rta_id: String) -> Option<RTAMetadata> {
         let token_id = format!("rta_{}", rta_id);
         let token = self.tokens.nft_token(token_id)?;
         if let Some(extra) = &token.metadata.as_ref()?.extra {
-            serde_json::from_str(extra).ok()
+            RTAMetadata::from_extra(extra).ok()
         } else {
             None
         }
     }
 
+    /// Same as `get_rta_metadata`, keyed by the full NFT `token_id` (`rta_<rta_id>`).
+    /// Returns `None` for tokens without the `rta_` prefix.
+    pub fn get_rta_metadata_by_token(&self, token_id: TokenId) -> Option<RTAMetadata> {
+        let rta_id = token_id.strip_prefix("rta_")?;
+        self.get_rta_metadata(rta_id.to_string())
+    }
+
+    /// Recomputes the sha256 of the RTA's `extra` and compares it with the stored
+    /// `reference_hash`. `false` when the RTA was minted without `hash_extra`.
+    pub fn verify_metadata_integrity(&self, rta_id: String) -> bool {
+        let token_id = format!("rta_{}", rta_id);
+        let token_metadata = self.tokens.token_metadata_by_id.as_ref()
+            .and_then(|by_id| by_id.get(&token_id))
+            .unwrap_or_else(|| RtaError::NotFound.panic());
+        match (&token_metadata.extra, &token_metadata.reference_hash) {
+            (Some(extra), Some(reference_hash)) => extra_hash(extra) == *reference_hash,
+            _ => false,
+        }
+    }
+
+    /// Resolves each ID in order, with `None` for unknown RTAs.
+    pub fn get_rtas_metadata(&self, rta_ids: Vec<String>) -> Vec<Option<RTAMetadata>> {
+        require_rta!(rta_ids.len() <= MAX_BATCH_SIZE, RtaError::BatchTooLarge(MAX_BATCH_SIZE));
+        rta_ids.into_iter().map(|rta_id| self.get_rta_metadata(rta_id)).collect()
+    }
+
+    pub fn get_chunk_cids(&self, rta_id: String, from_index: u32, limit: u32) -> Vec<String> {
+        let rta_metadata = self.get_rta_metadata(rta_id).unwrap_or_else(|| RtaError::NotFound.panic());
+        rta_metadata.chunk_cids
+            .into_iter()
+            .skip(from_index as usize)
+            .take(limit.min(MAX_CHUNK_PAGE_SIZE) as usize)
+            .collect()
+    }
+
+    /// One page of `(index, cid, owner)` for the RTA's chunks, with each owner looked up
+    /// by the chunk's index so callers don't have to correlate `chunk_cids` and `chunk_ownership`.
+    pub fn get_chunk_entries(&self, rta_id: String, from: u32, limit: u32) -> Vec<(u32, String, Option<String>)> {
+        let RTAMetadata { chunk_cids, mut chunk_ownership, .. } =
+            self.get_rta_metadata(rta_id).unwrap_or_else(|| RtaError::NotFound.panic());
+        chunk_cids
+            .into_iter()
+            .zip(0u32..)
+            .skip(from as usize)
+            .take(limit.min(MAX_CHUNK_PAGE_SIZE) as usize)
+            .map(|(cid, index)| (index, cid, chunk_ownership.remove(&index)))
+            .collect()
+    }
+
+    pub fn get_chunk_owner(&self, rta_id: String, chunk_index: u32) -> Option<String> {
+        self.get_rta_metadata(rta_id)?.chunk_ownership.remove(&chunk_index)
+    }
+
+    pub fn get_chunk_info(&self, rta_id: String, index: u32) -> Option<ChunkInfo> {
+        let rta_metadata = self.get_rta_metadata(rta_id)?;
+        let cid = rta_metadata.chunk_cids.get(index as usize)?.clone();
+        let meta = rta_metadata.chunk_meta.get(index as usize).cloned().unwrap_or_default();
+        Some(ChunkInfo {
+            cid,
+            owner: rta_metadata.chunk_ownership.get(&index).and_then(|owner| owner.parse().ok()),
+            size_bytes: meta.size_bytes,
+            duration_ms: meta.duration_ms,
+            content_sha256: meta.content_sha256,
+        })
+    }
+
+    /// Whether `provided_sha256` (hex, any case) matches the content hash recorded for chunk
+    /// `index`. `false` when the index is out of range or the chunk was added without one.
+    pub fn verify_chunk(&self, rta_id: String, index: u32, provided_sha256: String) -> bool {
+        let rta_metadata = self.get_rta_metadata(rta_id).unwrap_or_else(|| RtaError::NotFound.panic());
+        rta_metadata
+            .chunk_meta
+            .get(index as usize)
+            .and_then(|meta| meta.content_sha256.as_ref())
+            .is_some_and(|hash| hash.eq_ignore_ascii_case(&provided_sha256))
+    }
+
+    pub fn get_total_chunks(&self, rta_id: String) -> u32 {
+        self.get_rta_metadata(rta_id)
+            .map(|metadata| metadata.total_chunks)
+            .unwrap_or(0)
+    }
+
+    /// Scans one page of tokens (`from_index`/`limit` index the token set, not the
+    /// results) and returns the RTAs whose config names `creator`.
+    pub fn get_rtas_by_creator(&self, creator: String, from_index: Option<U128>, limit: Option<u64>) -> Vec<RTAMetadata> {
+        self.internal_scan_rtas(from_index, limit)
+            .filter(|rta_metadata| rta_metadata.config.creator == creator)
+            .collect()
+    }
+
+    /// Scans one page of tokens like `get_rtas_by_creator` and returns the RTAs that are
+    /// neither live nor closed: streams that ended without a master CID, as well as RTAs
+    /// that never went live.
+    pub fn get_unfinalized_rtas(&self, from_index: Option<U128>, limit: Option<u64>) -> Vec<String> {
+        self.internal_scan_rtas(from_index, limit)
+            .filter(|rta_metadata| !rta_metadata.is_live && !rta_metadata.is_closed)
+            .map(|rta_metadata| rta_metadata.rta_id)
+            .collect()
+    }
+
+    pub fn get_live_rtas(&self, from_index: Option<U128>, limit: Option<u64>) -> Vec<String> {
+        let start = from_index.map_or(0, |index| index.0 as usize);
+        let limit = limit.unwrap_or(DEFAULT_SCAN_LIMIT).min(MAX_SCAN_LIMIT) as usize;
+        self.live_rtas.iter().skip(start).take(limit).cloned().collect()
+    }
+
+    /// O(1): read from the token and live-set lengths and the closed counter.
+    pub fn get_rta_counts(&self) -> RtaCounts {
+        RtaCounts {
+            total: self.tokens.owner_by_id.len(),
+            live: self.live_rtas.len() as u64,
+            closed: self.closed_rtas,
+        }
+    }
+
     pub fn is_closed(&self, rta_id: String) -> bool {
         self.get_rta_metadata(rta_id)
             .map(|metadata| metadata.is_closed)
             .unwrap_or(false)
     }
 
+    /// Whether the RTA's `expires_at` has passed. `false` for unknown RTAs and RTAs without one.
+    pub fn is_expired(&self, rta_id: String) -> bool {
+        self.get_rta_metadata(rta_id)
+            .map(|metadata| metadata.config.is_expired())
+            .unwrap_or(false)
+    }
+
+    pub fn is_live(&self, rta_id: String) -> bool {
+        self.get_rta_metadata(rta_id)
+            .map(|metadata| metadata.is_live)
+            .unwrap_or(false)
+    }
+
+    /// Milliseconds streamed so far while live, or the length of the last
+    /// stream once it has ended. `None` if the RTA never went live.
+    pub fn live_duration_ms(&self, rta_id: String) -> Option<u64> {
+        let metadata = self.get_rta_metadata(rta_id)?;
+        let started_at = metadata.started_at?;
+        let until = if metadata.is_live { env::block_timestamp() } else { metadata.ended_at? };
+        Some(until.saturating_sub(started_at) / 1_000_000)
+    }
+
+    #[payable]
+    pub fn buy_ticket(&mut self, rta_id: String) {
+        let (_, _, rta_metadata) = self.internal_get_rta(&rta_id);
+        let config = &rta_metadata.config;
+        require_rta!(config.mode == "group", RtaError::NotGroupMode);
+        require_rta!(!rta_metadata.is_closed, RtaError::AlreadyClosed);
+        let ticket_price = parse_yocto(config.ticket_price.as_deref())
+            .unwrap_or_else(|| RtaError::InvalidTicketPrice.panic());
+        require_rta!(
+            env::attached_deposit().as_yoctonear() == ticket_price,
+            RtaError::IncorrectPayment(ticket_price)
+        );
+        let ticket_amount = config.ticket_amount.unwrap_or(0) as usize;
+        let account_id = env::predecessor_account_id();
+        let new_list = !self.ticket_holders.contains_key(&rta_id);
+        let bytes = account_list_entry_bytes(&rta_id, &account_id, new_list);
+        let holders = self.ticket_holders.entry(rta_id).or_default();
+        require_rta!(holders.len() < ticket_amount, RtaError::SoldOut);
+        holders.push(account_id.clone());
+        self.internal_charge_storage(&account_id, bytes);
+    }
+
+    pub fn get_ticket_holders(&self, rta_id: String) -> Vec<AccountId> {
+        self.ticket_holders.get(&rta_id).cloned().unwrap_or_default()
+    }
+
+    pub fn tickets_remaining(&self, rta_id: String) -> u32 {
+        let rta_metadata = self.get_rta_metadata(rta_id.clone()).unwrap_or_else(|| RtaError::NotFound.panic());
+        let sold = self.ticket_holders.get(&rta_id).map_or(0, |holders| holders.len() as u32);
+        rta_metadata.config.ticket_amount.unwrap_or(0).saturating_sub(sold)
+    }
+
+    #[payable]
+    pub fn pay_for_stream(&mut self, rta_id: String) {
+        let (_, _, rta_metadata) = self.internal_get_rta(&rta_id);
+        let config = &rta_metadata.config;
+        require_rta!(config.pay_per_stream, RtaError::NotPayPerStream);
+        require_rta!(!rta_metadata.is_closed, RtaError::AlreadyClosed);
+        let stream_price = parse_yocto(config.stream_price.as_deref())
+            .unwrap_or_else(|| RtaError::InvalidStreamPrice.panic());
+        let deposit = env::attached_deposit().as_yoctonear();
+        require_rta!(deposit == stream_price, RtaError::IncorrectPayment(stream_price));
+        let payer_id = env::predecessor_account_id();
+        self.stream_payments
+            .entry(rta_id.clone())
+            .or_default()
+            .push(StreamPayment {
+                payer_id: payer_id.clone(),
+                paid_at: env::block_timestamp(),
+                session: rta_metadata.current_session(),
+            });
+        RtaStreamPayment { rta_id: &rta_id, payer_id: &payer_id, amount: U128(deposit) }.emit();
+    }
+
+    /// Whether `account_id` paid for the current stream session: the live one, or the next one
+    /// while the RTA is not live. Payments for earlier sessions don't count once the RTA goes
+    /// live again.
+    pub fn has_paid(&self, rta_id: String, account_id: AccountId) -> bool {
+        let Some(rta_metadata) = self.get_rta_metadata(rta_id.clone()) else {
+            return false;
+        };
+        let session = rta_metadata.current_session();
+        self.stream_payments.get(&rta_id).is_some_and(|payments| {
+            payments.iter().any(|payment| payment.payer_id == account_id && payment.session == session)
+        })
+    }
+
+    pub fn join_stream(&mut self, rta_id: String, distance_meters: u32) {
+        let (_, _, rta_metadata) = self.internal_get_rta(&rta_id);
+        require_rta!(!rta_metadata.is_closed, RtaError::AlreadyClosed);
+        if let Some(max_distance) = rta_metadata.config.distance {
+            require_rta!(distance_meters <= max_distance, RtaError::OutOfRange);
+        }
+        let account_id = env::predecessor_account_id();
+        let new_list = !self.participants.contains_key(&rta_id);
+        let bytes = account_list_entry_bytes(&rta_id, &account_id, new_list);
+        let participants = self.participants.entry(rta_id).or_default();
+        if !participants.contains(&account_id) {
+            participants.push(account_id.clone());
+            self.internal_charge_storage(&account_id, bytes);
+        }
+    }
+
+    pub fn get_participants(&self, rta_id: String) -> Vec<AccountId> {
+        self.participants.get(&rta_id).cloned().unwrap_or_default()
+    }
+
+    pub fn withdraw(&mut self, amount: U128) -> Promise {
+        let owner_id = self.tokens.owner_id.clone();
+        require_rta!(env::predecessor_account_id() == owner_id, RtaError::NotContractOwner);
+        require_rta!(amount.0 <= self.available_balance().0, RtaError::InsufficientBalance);
+        Promise::new(owner_id).transfer(NearToken::from_yoctonear(amount.0))
+    }
+
+    /// Sets the protocol fee charged by `create_rta` and the account it is sent to.
+    pub fn set_fee_config(&mut self, fee_bps: u16, fee_recipient: AccountId) {
+        require_rta!(env::predecessor_account_id() == self.tokens.owner_id, RtaError::NotContractOwner);
+        require_rta!(fee_bps <= MAX_FEE_BPS, RtaError::FeeTooHigh(fee_bps));
+        self.fee_bps = fee_bps;
+        self.fee_recipient = fee_recipient;
+    }
+
+    pub fn get_fee_config(&self) -> FeeConfig {
+        FeeConfig { fee_bps: self.fee_bps, fee_recipient: self.fee_recipient.clone() }
+    }
+
+    /// Sets how many chunks `add_chunks` and `add_cids` may record per RTA. RTAs already
+    /// above a lowered cap keep their chunks but accept no more.
+    pub fn set_max_chunks_per_rta(&mut self, max_chunks_per_rta: u32) {
+        require_rta!(env::predecessor_account_id() == self.tokens.owner_id, RtaError::NotContractOwner);
+        self.max_chunks_per_rta = max_chunks_per_rta;
+    }
+
+    pub fn get_max_chunks_per_rta(&self) -> u32 {
+        self.max_chunks_per_rta
+    }
+
+    /// Contract balance not locked up for storage staking or owed back to NEP-145 depositors.
+    pub fn available_balance(&self) -> U128 {
+        let storage_cost = env::storage_byte_cost().saturating_mul(env::storage_usage().into());
+        U128(env::account_balance().saturating_sub(storage_cost).saturating_sub(self.storage_owed).as_yoctonear())
+    }
+
+    fn internal_scan_rtas(&self, from_index: Option<U128>, limit: Option<u64>) -> impl Iterator<Item = RTAMetadata> {
+        let limit = limit.unwrap_or(DEFAULT_SCAN_LIMIT).min(MAX_SCAN_LIMIT);
+        require_rta!(limit != 0, RtaError::InvalidLimit);
+        self.tokens.nft_tokens(from_index, Some(limit))
+            .into_iter()
+            .filter_map(|token| RTAMetadata::from_extra(token.metadata?.extra.as_ref()?).ok())
+    }
+
+    fn internal_get_rta(&self, rta_id: &str) -> (TokenId, TokenMetadata, RTAMetadata) {
+        self.internal_try_get_rta(rta_id).unwrap_or_else(|| RtaError::MetadataMissing.panic())
+    }
+
+    /// Like `internal_get_rta`, but returns `None` for a token minted without `extra`.
+    fn internal_try_get_rta(&self, rta_id: &str) -> Option<(TokenId, TokenMetadata, RTAMetadata)> {
+        let token_id = format!("rta_{}", rta_id);
+        require_rta!(self.tokens.owner_by_id.contains_key(&token_id), RtaError::NotFound);
+        let token_metadata = self.tokens.token_metadata_by_id.as_ref()
+            .and_then(|by_id| by_id.get(&token_id))
+            .unwrap_or_else(|| RtaError::MetadataMissing.panic());
+        let rta_metadata = RTAMetadata::from_extra(token_metadata.extra.as_ref()?)
+            .unwrap_or_else(|_| RtaError::InvalidMetadata.panic());
+        Some((token_id, token_metadata, rta_metadata))
+    }
+
+    fn internal_save_rta(&mut self, token_id: &TokenId, mut token_metadata: TokenMetadata, rta_metadata: &RTAMetadata) {
+        token_metadata.updated_at = Some(env::block_timestamp().to_string());
+        let extra = serde_json::to_string(rta_metadata).unwrap();
+        token_metadata.reference_hash = rta_metadata.config.hash_extra.then(|| extra_hash(&extra));
+        token_metadata.extra = Some(extra);
+        self.tokens.token_metadata_by_id.as_mut().unwrap().insert(token_id, &token_metadata);
+    }
+
+    /// Authorization shared by `add_chunks` (and so `add_cids`) and `can_add_chunks`:
+    /// collaborators may add chunks, anyone else needs a `can_update` delegation, and the RTA
+    /// must not be closed.
+    fn internal_check_add_chunks(&self, rta_id: &str, rta_metadata: Option<&RTAMetadata>, account: &AccountId) -> Result<(), RtaError> {
+        if !rta_metadata.is_some_and(|rta_metadata| rta_metadata.config.collaborators.contains(account)) {
+            let delegation = self.internal_get_delegation(rta_id, account).ok_or(RtaError::NoDelegation)?;
+            if !delegation.can_update {
+                return Err(RtaError::Unauthorized);
+            }
+        }
+        if rta_metadata.is_some_and(|rta_metadata| rta_metadata.is_closed) {
+            return Err(RtaError::AlreadyClosed);
+        }
+        Ok(())
+    }
+
+    /// Expired delegations are treated as absent.
+    fn internal_get_delegation(&self, rta_id: &str, delegate: &AccountId) -> Option<&Delegation> {
+        self.delegations.get(rta_id)?.iter().find(|d| &d.delegate == delegate && !d.is_expired())
+    }
+
+    /// Debits the storage cost of `bytes` from the account's NEP-145 balance.
+    fn internal_charge_storage(&mut self, account_id: &AccountId, bytes: u64) {
+        let cost = env::storage_byte_cost().saturating_mul(bytes.into());
+        let balance = self.storage_balances.get_mut(account_id).unwrap_or_else(|| RtaError::NotRegistered.panic());
+        require_rta!(balance.available >= cost, RtaError::InsufficientStorage(cost));
+        balance.available = balance.available.saturating_sub(cost);
+        self.storage_owed = self.storage_owed.saturating_sub(cost);
+    }
+
+    /// Requires the caller to own the RTA token and returns the owner.
+    fn assert_rta_owner(&self, token_id: &TokenId) -> AccountId {
+        let owner_id = self.tokens.owner_by_id.get(token_id).unwrap_or_else(|| RtaError::NotFound.panic());
+        require_rta!(env::predecessor_account_id() == owner_id, RtaError::NotOwner);
+        owner_id
+    }
+
+    /// Token owner, or a delegate holding `can_update`.
+    fn assert_owner_or_updater(&self, rta_id: &str, token_id: &TokenId) {
+        let caller = env::predecessor_account_id();
+        let is_owner = self.tokens.owner_by_id.get(token_id).is_some_and(|owner| owner == caller);
+        let is_updater = self.internal_get_delegation(rta_id, &caller).is_some_and(|d| d.can_update);
+        require_rta!(is_owner || is_updater, RtaError::Unauthorized);
+    }
+
+    /// Pre-funds the storage a stream will grow into:
+    ///
+    /// ```text
+    /// BASE_DEPOSIT
+    ///   + FILECOIN_DEPOSIT                                  if store_to_filecoin
+    ///   + ticket_amount * SEAT_STORAGE_BYTES * byte_cost    in group mode
+    ///   + expected_chunks * CHUNK_STORAGE_BYTES * byte_cost
+    /// ```
     fn calculate_minimum_deposit(&self, config: &RTAConfig) -> NearToken {
-        let mut base_cost = NearToken::from_millinear(10); // 0.01 NEAR base
-        
+        let byte_cost = env::storage_byte_cost();
+        let mut deposit = BASE_DEPOSIT;
         if config.store_to_filecoin {
-            base_cost = NearToken::from_yoctonear(base_cost.as_yoctonear() + 5_000_000_000_000_000_000_000); // +0.005 NEAR for Filecoin
+            deposit = deposit.saturating_add(FILECOIN_DEPOSIT);
         }
-        
-        if config.mode == "group" && config.ticket_amount.unwrap_or(0) > 10 {
-            base_cost = NearToken::from_yoctonear(base_cost.as_yoctonear() + 5_000_000_000_000_000_000_000); // +0.005 NEAR for large groups
+        if config.mode == "group" {
+            let seats = config.ticket_amount.unwrap_or(0) as u64;
+            deposit = deposit.saturating_add(byte_cost.saturating_mul((seats * SEAT_STORAGE_BYTES).into()));
         }
-        
-        base_cost
+        let chunks = config.expected_chunks.unwrap_or(DEFAULT_EXPECTED_CHUNKS) as u64;
+        deposit.saturating_add(byte_cost.saturating_mul((chunks * CHUNK_STORAGE_BYTES).into()))
     }
 }
 
@@ -250,11 +1362,11 @@ impl RTAv2 {
 impl NonFungibleTokenCore for RTAv2 {
     #[payable]
     fn nft_transfer(&mut self, _receiver_id: AccountId, _token_id: TokenId, _approval_id: Option<u64>, _memo: Option<String>) {
-        env::panic_str("Non-transferable NFT: transfer is disabled");
+        RtaError::NonTransferable("transfer").panic()
     }
     #[payable]
     fn nft_transfer_call(&mut self, _receiver_id: AccountId, _token_id: TokenId, _approval_id: Option<u64>, _memo: Option<String>, _msg: String) -> PromiseOrValue<bool> {
-        env::panic_str("Non-transferable NFT: transfer_call is disabled");
+        RtaError::NonTransferable("transfer_call").panic()
     }
     fn nft_token(&self, token_id: TokenId) -> Option<Token> {
         self.tokens.nft_token(token_id)
@@ -265,7 +1377,7 @@ impl NonFungibleTokenCore for RTAv2 {
 impl NonFungibleTokenResolver for RTAv2 {
     #[private]
     fn nft_resolve_transfer(&mut self, _previous_owner_id: AccountId, _receiver_id: AccountId, _token_id: TokenId, _approved_account_ids: Option<std::collections::HashMap<AccountId, u64>>) -> bool {
-        env::panic_str("Non-transferable NFT: resolve_transfer is disabled");
+        RtaError::NonTransferable("resolve_transfer").panic()
     }
 }
 
@@ -273,15 +1385,15 @@ impl NonFungibleTokenResolver for RTAv2 {
 impl NonFungibleTokenApproval for RTAv2 {
     #[payable]
     fn nft_approve(&mut self, _token_id: TokenId, _account_id: AccountId, _msg: Option<String>) -> Option<Promise> {
-        env::panic_str("Non-transferable NFT: approval is disabled");
+        RtaError::NonTransferable("approval").panic()
     }
     #[payable]
     fn nft_revoke(&mut self, _token_id: TokenId, _account_id: AccountId) {
-        env::panic_str("Non-transferable NFT: revoke is disabled");
+        RtaError::NonTransferable("revoke").panic()
     }
     #[payable]
     fn nft_revoke_all(&mut self, _token_id: TokenId) {
-        env::panic_str("Non-transferable NFT: revoke_all is disabled");
+        RtaError::NonTransferable("revoke_all").panic()
     }
     fn nft_is_approved(&self, _token_id: TokenId, _approved_account_id: AccountId, _approval_id: Option<u64>) -> bool {
         false
@@ -304,9 +1416,133 @@ impl NonFungibleTokenEnumeration for RTAv2 {
     }
 }
 
+#[near_bindgen]
+impl RTAv2 {
+    /// NEP-199: splits `balance` between the RTA's royalty accounts, with the
+    /// remainder going to the token owner.
+    pub fn nft_payout(&self, token_id: TokenId, balance: U128, max_len_payout: Option<u32>) -> Payout {
+        let owner_id = self.tokens.owner_by_id.get(&token_id).unwrap_or_else(|| RtaError::NotFound.panic());
+        let royalties = self.tokens.token_metadata_by_id.as_ref()
+            .and_then(|by_id| by_id.get(&token_id))
+            .and_then(|metadata| metadata.extra)
+            .and_then(|extra| RTAMetadata::from_extra(&extra).ok())
+            .and_then(|rta_metadata| rta_metadata.config.royalties)
+            .unwrap_or_default();
+        if let Some(max_len_payout) = max_len_payout {
+            require_rta!(royalties.len() < max_len_payout as usize, RtaError::TooManyPayoutReceivers);
+        }
+
+        let mut payout = Payout::default();
+        let mut remaining = balance.0;
+        for (account_id, bps) in royalties {
+            let amount = bps_share(bps, balance.0);
+            remaining -= amount;
+            payout.payout.entry(account_id).or_insert(U128(0)).0 += amount;
+        }
+        payout.payout.entry(owner_id).or_insert(U128(0)).0 += remaining;
+        payout
+    }
+
+    #[payable]
+    pub fn nft_transfer_payout(
+        &mut self,
+        _receiver_id: AccountId,
+        _token_id: TokenId,
+        _approval_id: Option<u64>,
+        _memo: Option<String>,
+        _balance: U128,
+        _max_len_payout: Option<u32>,
+    ) -> Payout {
+        RtaError::NonTransferable("transfer_payout").panic()
+    }
+}
+
 #[near_bindgen]
 impl NonFungibleTokenMetadataProvider for RTAv2 {
     fn nft_metadata(&self) -> NFTContractMetadata {
         self.metadata.get().unwrap()
     }
-}
\ No newline at end of file
+}
+#[near_bindgen]
+impl StorageManagement for RTAv2 {
+    #[payable]
+    fn storage_deposit(&mut self, account_id: Option<AccountId>, registration_only: Option<bool>) -> StorageBalance {
+        let amount = env::attached_deposit();
+        let account_id = account_id.unwrap_or_else(env::predecessor_account_id);
+        let min_balance = self.storage_balance_bounds().min;
+        let refund = match self.storage_balances.get_mut(&account_id) {
+            Some(balance) => {
+                if registration_only.unwrap_or(false) {
+                    amount
+                } else {
+                    balance.total = balance.total.saturating_add(amount);
+                    balance.available = balance.available.saturating_add(amount);
+                    self.storage_owed = self.storage_owed.saturating_add(amount);
+                    NearToken::from_yoctonear(0)
+                }
+            }
+            None => {
+                require_rta!(amount >= min_balance, RtaError::InsufficientDeposit(min_balance));
+                let deposit = if registration_only.unwrap_or(false) { min_balance } else { amount };
+                self.storage_balances.insert(
+                    account_id.clone(),
+                    StorageBalance { total: deposit, available: deposit.saturating_sub(min_balance) },
+                );
+                self.storage_owed = self.storage_owed.saturating_add(deposit);
+                amount.saturating_sub(deposit)
+            }
+        };
+        if !refund.is_zero() {
+            Promise::new(env::predecessor_account_id()).transfer(refund);
+        }
+        self.storage_balance_of(account_id).unwrap()
+    }
+
+    #[payable]
+    fn storage_withdraw(&mut self, amount: Option<NearToken>) -> StorageBalance {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        let balance = self.storage_balances.get_mut(&account_id).unwrap_or_else(|| RtaError::NotRegistered.panic());
+        let amount = amount.unwrap_or(balance.available);
+        require_rta!(amount <= balance.available, RtaError::InsufficientStorage(amount));
+        balance.available = balance.available.saturating_sub(amount);
+        balance.total = balance.total.saturating_sub(amount);
+        self.storage_owed = self.storage_owed.saturating_sub(amount);
+        if !amount.is_zero() {
+            Promise::new(account_id.clone()).transfer(amount);
+        }
+        self.storage_balance_of(account_id).unwrap()
+    }
+
+    /// Frees the registration and refunds the unspent balance. Storage already
+    /// spent on ticket holder and participant entries is not refunded, so
+    /// unregistering such an account requires `force`.
+    #[payable]
+    fn storage_unregister(&mut self, force: Option<bool>) -> bool {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        let min_balance = self.storage_balance_bounds().min;
+        let Some(balance) = self.storage_balances.get(&account_id) else {
+            return false;
+        };
+        let refund = balance.available.saturating_add(min_balance);
+        require_rta!(force.unwrap_or(false) || refund >= balance.total, RtaError::StorageInUse);
+        self.storage_balances.remove(&account_id);
+        self.storage_owed = self.storage_owed.saturating_sub(refund);
+        Promise::new(account_id).transfer(refund);
+        true
+    }
+
+    fn storage_balance_bounds(&self) -> StorageBalanceBounds {
+        StorageBalanceBounds {
+            min: env::storage_byte_cost().saturating_mul(STORAGE_BALANCE_RECORD_BYTES.into()),
+            max: None,
+        }
+    }
+
+    fn storage_balance_of(&self, account_id: AccountId) -> Option<StorageBalance> {
+        self.storage_balances
+            .get(&account_id)
+            .map(|balance| StorageBalance { total: balance.total, available: balance.available })
+    }
+}
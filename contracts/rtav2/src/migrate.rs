@@ -0,0 +1,187 @@
+/*!
+State migration for upgrading the deployed factory in place.
+
+[`OldRTAv2`] is the layout the factory was first deployed with: the NFT core,
+its contract metadata, and a single delegation per RTA. `migrate` reads it and
+builds the current [`RTAv2`], keeping every minted token.
+*/
+
+use crate::*;
+
+#[derive(BorshDeserialize)]
+#[cfg_attr(test, derive(BorshSerialize))]
+struct OldDelegation {
+    delegate: AccountId,
+    can_update: bool,
+    can_finalize: bool,
+}
+
+#[derive(BorshDeserialize)]
+#[cfg_attr(test, derive(BorshSerialize))]
+struct OldRTAv2 {
+    tokens: NonFungibleToken,
+    metadata: LazyOption<NFTContractMetadata>,
+    delegations: HashMap<String, OldDelegation>,
+}
+
+#[near_bindgen]
+impl RTAv2 {
+    /// Upgrades state written by the first deployment. Delegations carry over without an
+    /// expiry; `live_rtas` and the closed counter are rebuilt by reading every token, so
+    /// the token count must fit in one call's gas.
+    #[private]
+    #[init(ignore_state)]
+    pub fn migrate() -> Self {
+        let old: OldRTAv2 = env::state_read().unwrap_or_else(|| RtaError::NotInitialized.panic());
+        let delegations = old
+            .delegations
+            .into_iter()
+            .map(|(rta_id, delegation)| {
+                let delegation = Delegation {
+                    delegate: delegation.delegate,
+                    can_update: delegation.can_update,
+                    can_finalize: delegation.can_finalize,
+                    expires_at: None,
+                };
+                (rta_id, vec![delegation])
+            })
+            .collect();
+
+        let mut contract = Self {
+            fee_recipient: old.tokens.owner_id.clone(),
+            tokens: old.tokens,
+            metadata: old.metadata,
+            delegations,
+            ticket_holders: HashMap::new(),
+            participants: HashMap::new(),
+            live_rtas: IterableSet::new(StorageKey::LiveRtas),
+            stream_payments: HashMap::new(),
+            storage_balances: LookupMap::new(StorageKey::StorageBalances),
+            storage_owed: NearToken::from_yoctonear(0),
+            closed_rtas: 0,
+            fee_bps: 0,
+            max_chunks_per_rta: DEFAULT_MAX_CHUNKS_PER_RTA,
+        };
+
+        let rtas: Vec<RTAMetadata> = contract
+            .tokens
+            .nft_tokens(None, None)
+            .into_iter()
+            .filter_map(|token| RTAMetadata::from_extra(token.metadata?.extra.as_ref()?).ok())
+            .collect();
+        for rta_metadata in rtas {
+            if rta_metadata.is_live {
+                contract.live_rtas.insert(rta_metadata.rta_id);
+            } else if rta_metadata.is_closed {
+                contract.closed_rtas += 1;
+            }
+        }
+        contract
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::testing_env;
+
+    /// `extra` as the first deployment wrote it, before any of the `#[serde(default)]` fields.
+    fn legacy_extra(rta_id: &str, is_live: bool, is_closed: bool) -> String {
+        serde_json::json!({
+            "rta_id": rta_id,
+            "config": {
+                "mode": "solo",
+                "store_to_filecoin": false,
+                "pay_per_stream": false,
+                "creator": accounts(1),
+                "created_at": 0,
+            },
+            "is_live": is_live,
+            "is_closed": is_closed,
+            "chunk_cids": ["cid0"],
+            "chunk_ownership": { "0": accounts(3) },
+            "total_chunks": 1,
+            "filecoin_master_cid": null,
+        })
+        .to_string()
+    }
+
+    fn mint_legacy(tokens: &mut NonFungibleToken, rta_id: &str, is_live: bool, is_closed: bool) {
+        let token_metadata = TokenMetadata {
+            title: Some(format!("VibesFlow RTA #{}", rta_id)),
+            description: None,
+            media: None,
+            media_hash: None,
+            copies: Some(1),
+            issued_at: None,
+            expires_at: None,
+            starts_at: None,
+            updated_at: None,
+            extra: Some(legacy_extra(rta_id, is_live, is_closed)),
+            reference: None,
+            reference_hash: None,
+        };
+        tokens.internal_mint_with_refund(format!("rta_{}", rta_id), accounts(1), Some(token_metadata), None);
+    }
+
+    #[test]
+    fn migrate_keeps_tokens_and_delegations() {
+        testing_env!(VMContextBuilder::new().predecessor_account_id(accounts(0)).build());
+        let mut tokens = NonFungibleToken::new(
+            StorageKey::NonFungibleToken,
+            accounts(0),
+            Some(StorageKey::TokenMetadata),
+            Some(StorageKey::Enumeration),
+            Some(StorageKey::Approval),
+        );
+        mint_legacy(&mut tokens, "live", true, false);
+        mint_legacy(&mut tokens, "closed", false, true);
+        mint_legacy(&mut tokens, "idle", false, false);
+        let metadata = NFTContractMetadata {
+            spec: NFT_METADATA_SPEC.to_string(),
+            name: "VibesFlow Real-Time Assets".to_string(),
+            symbol: "VRTA".to_string(),
+            icon: None,
+            base_uri: None,
+            reference: None,
+            reference_hash: None,
+        };
+        let delegation = OldDelegation { delegate: accounts(2), can_update: true, can_finalize: false };
+        env::state_write(&OldRTAv2 {
+            tokens,
+            metadata: LazyOption::new(StorageKey::Metadata, Some(&metadata)),
+            delegations: HashMap::from([("live".to_string(), delegation)]),
+        });
+
+        let contract = RTAv2::migrate();
+
+        assert_eq!(contract.nft_total_supply().0, 3);
+        assert_eq!(contract.nft_token("rta_idle".to_string()).unwrap().owner_id, accounts(1));
+        assert_eq!(contract.nft_metadata().symbol, "VRTA");
+        assert_eq!(contract.get_fee_config().fee_recipient, accounts(0));
+
+        let delegations = contract.get_delegations("live".to_string());
+        assert_eq!(delegations.len(), 1);
+        assert_eq!(delegations[0].delegate, accounts(2));
+        assert!(delegations[0].can_update && !delegations[0].can_finalize);
+        assert_eq!(delegations[0].expires_at, None);
+
+        assert_eq!(contract.get_live_rtas(None, None), vec!["live".to_string()]);
+        let counts = contract.get_rta_counts();
+        assert_eq!((counts.total, counts.live, counts.closed), (3, 1, 1));
+
+        let rta_metadata = contract.get_rta_metadata("live".to_string()).unwrap();
+        assert_eq!(rta_metadata.chunk_cids, vec!["cid0".to_string()]);
+        assert_eq!(rta_metadata.chunk_meta.len(), 1);
+        assert_eq!(rta_metadata.schema_version, RTA_SCHEMA_VERSION);
+        assert_eq!(contract.get_chunk_owner("live".to_string(), 0), Some(accounts(3).to_string()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Contract state not found")]
+    fn migrate_without_state_fails() {
+        testing_env!(VMContextBuilder::new().build());
+        RTAv2::migrate();
+    }
+}
@@ -0,0 +1,643 @@
+use super::*;
+use near_sdk::test_utils::{accounts, VMContextBuilder};
+use near_sdk::testing_env;
+
+fn context(predecessor: AccountId) -> VMContextBuilder {
+    let mut builder = VMContextBuilder::new();
+    builder
+        .current_account_id("factory.near".parse().unwrap())
+        .predecessor_account_id(predecessor.clone())
+        .signer_account_id(predecessor);
+    builder
+}
+
+fn set_caller(predecessor: AccountId) {
+    testing_env!(context(predecessor).build());
+}
+
+fn set_caller_with_deposit(predecessor: AccountId, deposit: NearToken) {
+    testing_env!(context(predecessor).attached_deposit(deposit).build());
+}
+
+fn solo_config(creator: &AccountId) -> RTAConfig {
+    RTAConfig {
+        mode: "solo".to_string(),
+        store_to_filecoin: false,
+        distance: None,
+        ticket_amount: None,
+        ticket_price: None,
+        pay_per_stream: false,
+        stream_price: None,
+        creator: creator.to_string(),
+        created_at: 0,
+        royalties: None,
+        media: None,
+        media_hash: None,
+        collaborators: Vec::new(),
+        expected_chunks: None,
+        hash_extra: false,
+        expires_at: None,
+    }
+}
+
+/// Contract owned by `accounts(0)` with one solo RTA `rta_id` minted to `creator`.
+fn setup_with_rta(rta_id: &str, creator: AccountId) -> RTAv2 {
+    set_caller(accounts(0));
+    let mut contract = RTAv2::new_default_meta(accounts(0));
+    set_caller_with_deposit(creator.clone(), NearToken::from_near(1));
+    contract.create_rta(rta_id.to_string(), solo_config(&creator), creator);
+    contract
+}
+
+fn update_permissions() -> Vec<String> {
+    vec!["update_chunks".to_string()]
+}
+
+#[test]
+fn rta_owner_can_delegate() {
+    let mut contract = setup_with_rta("s1", accounts(1));
+    set_caller(accounts(1));
+    contract.delegate_rta_permissions("s1".to_string(), accounts(2), update_permissions(), None);
+    assert!(contract.check_delegation("s1".to_string(), accounts(2)));
+}
+
+#[test]
+fn contract_owner_can_delegate() {
+    let mut contract = setup_with_rta("s1", accounts(1));
+    set_caller(accounts(0));
+    contract.delegate_rta_permissions("s1".to_string(), accounts(2), update_permissions(), None);
+    assert!(contract.check_delegation("s1".to_string(), accounts(2)));
+}
+
+#[test]
+#[should_panic(expected = "Only the RTA owner can call this method")]
+fn stranger_cannot_delegate_to_itself() {
+    let mut contract = setup_with_rta("s1", accounts(1));
+    set_caller(accounts(3));
+    contract.delegate_rta_permissions("s1".to_string(), accounts(3), update_permissions(), None);
+}
+
+#[test]
+#[should_panic(expected = "Only the RTA owner can call this method")]
+fn revoked_delegate_cannot_re_add_itself() {
+    let mut contract = setup_with_rta("s1", accounts(1));
+    set_caller(accounts(1));
+    contract.delegate_rta_permissions("s1".to_string(), accounts(2), update_permissions(), None);
+    contract.revoke_delegation("s1".to_string(), Some(accounts(2)));
+    set_caller(accounts(2));
+    contract.delegate_rta_permissions("s1".to_string(), accounts(2), update_permissions(), None);
+}
+
+#[test]
+#[should_panic(expected = "RTA not found")]
+fn delegating_on_unknown_rta_fails() {
+    let mut contract = setup_with_rta("s1", accounts(1));
+    set_caller(accounts(1));
+    contract.delegate_rta_permissions("missing".to_string(), accounts(2), update_permissions(), None);
+}
+
+/// `setup_with_rta` plus an `update_chunks` delegation to `accounts(2)`, left as the caller.
+fn setup_with_updater(rta_id: &str) -> RTAv2 {
+    let mut contract = setup_with_rta(rta_id, accounts(1));
+    set_caller(accounts(1));
+    contract.delegate_rta_permissions(rta_id.to_string(), accounts(2), update_permissions(), None);
+    set_caller(accounts(2));
+    contract
+}
+
+#[test]
+fn chunk_ownership_keys_match_chunk_cids_positions() {
+    let mut contract = setup_with_updater("s1");
+    let added = contract.add_cids(
+        "s1".to_string(),
+        vec!["cid0".to_string(), "cid1".to_string(), "cid2".to_string()],
+        vec![accounts(3), accounts(4), accounts(5)],
+    );
+    assert_eq!(added, 3);
+
+    let rta_metadata = contract.get_rta_metadata("s1".to_string()).unwrap();
+    for (index, _) in rta_metadata.chunk_cids.iter().enumerate() {
+        assert!(rta_metadata.chunk_ownership.contains_key(&(index as u32)));
+    }
+    assert_eq!(rta_metadata.chunk_ownership.len(), rta_metadata.chunk_cids.len());
+    assert_eq!(contract.get_chunk_owner("s1".to_string(), 0), Some(accounts(3).to_string()));
+    assert_eq!(contract.get_chunk_owner("s1".to_string(), 2), Some(accounts(5).to_string()));
+    assert_eq!(contract.get_chunk_owner("s1".to_string(), 3), None);
+}
+
+#[test]
+fn chunk_ownership_keys_continue_across_batches() {
+    let mut contract = setup_with_updater("s1");
+    contract.add_cids("s1".to_string(), vec!["cid0".to_string()], vec![accounts(3)]);
+    contract.add_cids("s1".to_string(), vec!["cid1".to_string()], vec![accounts(4)]);
+
+    let entries = contract.get_chunk_entries("s1".to_string(), 0, 10);
+    assert_eq!(
+        entries,
+        vec![
+            (0, "cid0".to_string(), Some(accounts(3).to_string())),
+            (1, "cid1".to_string(), Some(accounts(4).to_string())),
+        ]
+    );
+}
+
+#[test]
+fn chunks_without_owner_have_no_ownership_entry() {
+    let mut contract = setup_with_updater("s1");
+    contract.add_cids("s1".to_string(), vec!["cid0".to_string(), "cid1".to_string()], vec![accounts(3)]);
+    assert_eq!(contract.get_chunk_owner("s1".to_string(), 0), Some(accounts(3).to_string()));
+    assert_eq!(contract.get_chunk_owner("s1".to_string(), 1), None);
+}
+
+#[test]
+fn get_chunk_owner_of_unknown_rta_is_none() {
+    let contract = setup_with_rta("s1", accounts(1));
+    assert_eq!(contract.get_chunk_owner("missing".to_string(), 0), None);
+}
+
+#[test]
+#[should_panic(expected = "No delegation")]
+fn add_cids_without_delegation_fails() {
+    let mut contract = setup_with_rta("s1", accounts(1));
+    set_caller(accounts(2));
+    contract.add_cids("s1".to_string(), vec!["cid0".to_string()], vec![accounts(3)]);
+}
+
+fn group_config(creator: &AccountId) -> RTAConfig {
+    RTAConfig {
+        mode: "group".to_string(),
+        ticket_amount: Some(5),
+        ticket_price: Some("1000".to_string()),
+        ..solo_config(creator)
+    }
+}
+
+/// Registers `account` for NEP-145 storage with enough balance for a few entries.
+fn register_storage(contract: &mut RTAv2, account: AccountId) {
+    set_caller_with_deposit(account, NearToken::from_millinear(100));
+    contract.storage_deposit(None, None);
+}
+
+fn finalize_as_owner(contract: &mut RTAv2, rta_id: &str) {
+    set_caller(accounts(1));
+    contract.delegate_rta_permissions(rta_id.to_string(), accounts(1), vec!["finalize_rta".to_string()], None);
+    contract.finalize(rta_id.to_string(), "master".to_string());
+}
+
+#[test]
+fn burn_never_live_rta() {
+    let mut contract = setup_with_rta("s1", accounts(1));
+    set_caller(accounts(1));
+    contract.burn_rta("s1".to_string());
+    assert!(contract.get_rta_metadata("s1".to_string()).is_none());
+    assert_eq!(contract.get_rta_counts().total, 0);
+}
+
+#[test]
+fn burn_closed_rta() {
+    let mut contract = setup_with_rta("s1", accounts(1));
+    set_caller(accounts(1));
+    contract.set_live("s1".to_string());
+    finalize_as_owner(&mut contract, "s1");
+    assert_eq!(contract.get_rta_counts().closed, 1);
+    contract.burn_rta("s1".to_string());
+    assert_eq!(contract.get_rta_counts().closed, 0);
+    assert!(contract.get_delegations("s1".to_string()).is_empty());
+}
+
+#[test]
+#[should_panic(expected = "RTA is not closed")]
+fn burn_ended_but_unfinalized_rta_fails() {
+    let mut contract = setup_with_rta("s1", accounts(1));
+    set_caller(accounts(1));
+    contract.set_live("s1".to_string());
+    contract.end_stream("s1".to_string());
+    contract.burn_rta("s1".to_string());
+}
+
+#[test]
+#[should_panic(expected = "RTA is live; end the stream first")]
+fn burn_live_rta_fails() {
+    let mut contract = setup_with_rta("s1", accounts(1));
+    set_caller(accounts(1));
+    contract.set_live("s1".to_string());
+    contract.burn_rta("s1".to_string());
+}
+
+#[test]
+#[should_panic(expected = "Only the RTA owner can call this method")]
+fn burn_by_non_owner_fails() {
+    let mut contract = setup_with_rta("s1", accounts(1));
+    set_caller(accounts(2));
+    contract.burn_rta("s1".to_string());
+}
+
+#[test]
+#[should_panic(expected = "RTA has ticket holders or stream payments and cannot be burned")]
+fn burn_with_ticket_holders_fails() {
+    set_caller(accounts(0));
+    let mut contract = RTAv2::new_default_meta(accounts(0));
+    set_caller_with_deposit(accounts(1), NearToken::from_near(1));
+    contract.create_rta("g1".to_string(), group_config(&accounts(1)), accounts(1));
+    register_storage(&mut contract, accounts(2));
+    set_caller_with_deposit(accounts(2), NearToken::from_yoctonear(1000));
+    contract.buy_ticket("g1".to_string());
+
+    set_caller(accounts(1));
+    contract.burn_rta("g1".to_string());
+}
+
+#[test]
+#[should_panic(expected = "RTA has ticket holders or stream payments and cannot be burned")]
+fn burn_with_stream_payments_fails() {
+    set_caller(accounts(0));
+    let mut contract = RTAv2::new_default_meta(accounts(0));
+    let config = RTAConfig {
+        pay_per_stream: true,
+        stream_price: Some("500".to_string()),
+        ..solo_config(&accounts(1))
+    };
+    set_caller_with_deposit(accounts(1), NearToken::from_near(1));
+    contract.create_rta("p1".to_string(), config, accounts(1));
+    set_caller_with_deposit(accounts(2), NearToken::from_yoctonear(500));
+    contract.pay_for_stream("p1".to_string());
+
+    set_caller(accounts(1));
+    contract.burn_rta("p1".to_string());
+}
+
+fn setup_pay_per_stream(rta_id: &str) -> RTAv2 {
+    set_caller(accounts(0));
+    let mut contract = RTAv2::new_default_meta(accounts(0));
+    let config = RTAConfig {
+        pay_per_stream: true,
+        stream_price: Some("500".to_string()),
+        ..solo_config(&accounts(1))
+    };
+    set_caller_with_deposit(accounts(1), NearToken::from_near(1));
+    contract.create_rta(rta_id.to_string(), config, accounts(1));
+    contract
+}
+
+fn pay_for_stream(contract: &mut RTAv2, rta_id: &str, payer: AccountId) {
+    set_caller_with_deposit(payer, NearToken::from_yoctonear(500));
+    contract.pay_for_stream(rta_id.to_string());
+}
+
+#[test]
+fn payment_before_going_live_counts_for_the_first_session() {
+    let mut contract = setup_pay_per_stream("p1");
+    pay_for_stream(&mut contract, "p1", accounts(2));
+    assert!(contract.has_paid("p1".to_string(), accounts(2)));
+    set_caller(accounts(1));
+    contract.set_live("p1".to_string());
+    assert!(contract.has_paid("p1".to_string(), accounts(2)));
+    assert!(!contract.has_paid("p1".to_string(), accounts(3)));
+}
+
+#[test]
+fn payment_does_not_carry_over_to_the_next_session() {
+    let mut contract = setup_pay_per_stream("p1");
+    set_caller(accounts(1));
+    contract.set_live("p1".to_string());
+    pay_for_stream(&mut contract, "p1", accounts(2));
+    assert!(contract.has_paid("p1".to_string(), accounts(2)));
+
+    set_caller(accounts(1));
+    contract.end_stream("p1".to_string());
+    assert!(!contract.has_paid("p1".to_string(), accounts(2)));
+    contract.set_live("p1".to_string());
+    assert!(!contract.has_paid("p1".to_string(), accounts(2)));
+
+    pay_for_stream(&mut contract, "p1", accounts(2));
+    assert!(contract.has_paid("p1".to_string(), accounts(2)));
+}
+
+#[test]
+fn has_paid_for_unknown_rta_is_false() {
+    let contract = setup_pay_per_stream("p1");
+    assert!(!contract.has_paid("missing".to_string(), accounts(2)));
+}
+
+#[test]
+#[should_panic(expected = "Incorrect payment: attached deposit must equal 500 yoctoNEAR")]
+fn pay_for_stream_with_wrong_amount_fails() {
+    let mut contract = setup_pay_per_stream("p1");
+    set_caller_with_deposit(accounts(2), NearToken::from_yoctonear(499));
+    contract.pay_for_stream("p1".to_string());
+}
+
+#[test]
+#[should_panic(expected = "RTA already exists")]
+fn create_rta_twice_fails() {
+    let mut contract = setup_with_rta("s1", accounts(1));
+    set_caller_with_deposit(accounts(1), NearToken::from_near(1));
+    contract.create_rta("s1".to_string(), solo_config(&accounts(1)), accounts(1));
+}
+
+#[test]
+#[should_panic(expected = "RTA already exists")]
+fn create_rta_with_taken_id_for_another_receiver_fails() {
+    let mut contract = setup_with_rta("s1", accounts(1));
+    set_caller_with_deposit(accounts(2), NearToken::from_near(1));
+    contract.create_rta("s1".to_string(), solo_config(&accounts(2)), accounts(2));
+}
+
+#[test]
+fn create_rta_with_distinct_ids() {
+    let mut contract = setup_with_rta("s1", accounts(1));
+    set_caller_with_deposit(accounts(1), NearToken::from_near(1));
+    contract.create_rta("s2".to_string(), solo_config(&accounts(1)), accounts(1));
+    assert_eq!(contract.get_rta_counts().total, 2);
+}
+
+fn storage_staked() -> u128 {
+    env::storage_byte_cost().saturating_mul(env::storage_usage().into()).as_yoctonear()
+}
+
+#[test]
+fn available_balance_reserves_storage_deposits() {
+    set_caller(accounts(0));
+    let mut contract = RTAv2::new_default_meta(accounts(0));
+    set_caller_with_deposit(accounts(2), NearToken::from_near(1));
+    contract.storage_deposit(None, None);
+    let expected = env::account_balance().as_yoctonear() - storage_staked() - NearToken::from_near(1).as_yoctonear();
+    assert_eq!(contract.available_balance().0, expected);
+
+    set_caller_with_deposit(accounts(2), NearToken::from_yoctonear(1));
+    contract.storage_withdraw(Some(NearToken::from_millinear(400)));
+    let expected = env::account_balance().as_yoctonear() - storage_staked() - NearToken::from_millinear(600).as_yoctonear();
+    assert_eq!(contract.available_balance().0, expected);
+
+    contract.storage_unregister(None);
+    assert_eq!(contract.available_balance().0, env::account_balance().as_yoctonear() - storage_staked());
+}
+
+#[test]
+fn storage_spent_on_entries_is_released_from_the_reserve() {
+    set_caller(accounts(0));
+    let mut contract = RTAv2::new_default_meta(accounts(0));
+    set_caller_with_deposit(accounts(1), NearToken::from_near(1));
+    contract.create_rta("g1".to_string(), group_config(&accounts(1)), accounts(1));
+    register_storage(&mut contract, accounts(2));
+    let before = contract.storage_owed;
+
+    set_caller_with_deposit(accounts(2), NearToken::from_yoctonear(1000));
+    contract.buy_ticket("g1".to_string());
+    let balance = contract.storage_balance_of(accounts(2)).unwrap();
+    let min_balance = contract.storage_balance_bounds().min;
+    let spent = balance.total.saturating_sub(balance.available).saturating_sub(min_balance);
+    assert!(!spent.is_zero());
+    assert_eq!(before.saturating_sub(contract.storage_owed), spent);
+}
+
+#[test]
+#[should_panic(expected = "Insufficient balance above the storage staking floor")]
+fn owner_cannot_withdraw_storage_deposits() {
+    set_caller(accounts(0));
+    let mut contract = RTAv2::new_default_meta(accounts(0));
+    set_caller_with_deposit(accounts(2), NearToken::from_near(1));
+    contract.storage_deposit(None, None);
+    set_caller(accounts(0));
+    let available = contract.available_balance().0;
+    contract.withdraw(U128(available + 1));
+}
+
+fn bytes_cost(bytes: u64) -> NearToken {
+    env::storage_byte_cost().saturating_mul(bytes.into())
+}
+
+#[test]
+fn minimum_deposit_solo() {
+    let contract = setup_with_rta("s1", accounts(1));
+    let expected = BASE_DEPOSIT.saturating_add(bytes_cost(DEFAULT_EXPECTED_CHUNKS as u64 * CHUNK_STORAGE_BYTES));
+    assert_eq!(contract.calculate_minimum_deposit(&solo_config(&accounts(1))), expected);
+}
+
+#[test]
+fn minimum_deposit_scales_with_expected_chunks() {
+    let contract = setup_with_rta("s1", accounts(1));
+    let config = RTAConfig { expected_chunks: Some(1_000), ..solo_config(&accounts(1)) };
+    let expected = BASE_DEPOSIT.saturating_add(bytes_cost(1_000 * CHUNK_STORAGE_BYTES));
+    assert_eq!(contract.calculate_minimum_deposit(&config), expected);
+}
+
+#[test]
+fn minimum_deposit_small_group() {
+    let contract = setup_with_rta("s1", accounts(1));
+    let config = RTAConfig { ticket_amount: Some(3), ..group_config(&accounts(1)) };
+    let solo = contract.calculate_minimum_deposit(&solo_config(&accounts(1)));
+    assert_eq!(contract.calculate_minimum_deposit(&config), solo.saturating_add(bytes_cost(3 * SEAT_STORAGE_BYTES)));
+}
+
+#[test]
+fn minimum_deposit_large_group_scales_per_seat() {
+    let contract = setup_with_rta("s1", accounts(1));
+    let small = RTAConfig { ticket_amount: Some(10), ..group_config(&accounts(1)) };
+    let large = RTAConfig { ticket_amount: Some(1_000), ..group_config(&accounts(1)) };
+    let difference = contract
+        .calculate_minimum_deposit(&large)
+        .saturating_sub(contract.calculate_minimum_deposit(&small));
+    assert_eq!(difference, bytes_cost(990 * SEAT_STORAGE_BYTES));
+}
+
+#[test]
+fn minimum_deposit_with_filecoin() {
+    let contract = setup_with_rta("s1", accounts(1));
+    for config in [solo_config(&accounts(1)), group_config(&accounts(1))] {
+        let with_filecoin = RTAConfig { store_to_filecoin: true, ..config.clone() };
+        assert_eq!(
+            contract.calculate_minimum_deposit(&with_filecoin),
+            contract.calculate_minimum_deposit(&config).saturating_add(FILECOIN_DEPOSIT)
+        );
+    }
+}
+
+#[test]
+fn quote_covers_minimum_deposit() {
+    let contract = setup_with_rta("s1", accounts(1));
+    let config = RTAConfig { store_to_filecoin: true, ..group_config(&accounts(1)) };
+    assert!(contract.quote_minimum_deposit(config.clone()).0 >= contract.calculate_minimum_deposit(&config).as_yoctonear());
+}
+
+#[test]
+#[should_panic(expected = "Group mode requires a non-zero ticket_amount")]
+fn quote_for_group_without_tickets_fails() {
+    let contract = setup_with_rta("s1", accounts(1));
+    contract.quote_minimum_deposit(RTAConfig { ticket_amount: None, ..group_config(&accounts(1)) });
+}
+
+#[test]
+#[should_panic(expected = "Insufficient deposit")]
+fn create_rta_below_minimum_deposit_fails() {
+    let mut contract = setup_with_rta("s1", accounts(1));
+    let config = RTAConfig { ticket_amount: Some(1_000), ..group_config(&accounts(1)) };
+    let required = contract.quote_minimum_deposit(config.clone()).0;
+    set_caller_with_deposit(accounts(1), NearToken::from_yoctonear(required - 1));
+    contract.create_rta("g1".to_string(), config, accounts(1));
+}
+
+/// Total NEAR transferred to `account` by the receipts the last call created.
+fn transferred_to(account: &AccountId) -> NearToken {
+    near_sdk::test_utils::get_created_receipts()
+        .into_iter()
+        .filter(|receipt| &receipt.receiver_id == account)
+        .flat_map(|receipt| receipt.actions)
+        .filter_map(|action| match action {
+            near_sdk::mock::MockAction::Transfer { deposit, .. } => Some(deposit),
+            _ => None,
+        })
+        .fold(NearToken::from_yoctonear(0), NearToken::saturating_add)
+}
+
+/// Mints `rta_id` to `accounts(1)` attaching `deposit`, with a 5% fee sent to `accounts(4)`.
+/// Returns the fee and the refund it paid out.
+fn create_with_fee(rta_id: &str, deposit: impl FnOnce(&RTAv2) -> NearToken) -> (NearToken, NearToken) {
+    set_caller(accounts(0));
+    let mut contract = RTAv2::new_default_meta(accounts(0));
+    contract.set_fee_config(500, accounts(4));
+    let deposit = deposit(&contract);
+    set_caller_with_deposit(accounts(1), deposit);
+    contract.create_rta(rta_id.to_string(), solo_config(&accounts(1)), accounts(1));
+    (transferred_to(&accounts(4)), transferred_to(&accounts(1)))
+}
+
+#[test]
+fn fee_is_charged_on_the_retained_deposit() {
+    let (fee, refund) = create_with_fee("s1", |contract| {
+        NearToken::from_yoctonear(contract.quote_minimum_deposit(solo_config(&accounts(1))).0)
+    });
+    let retained = RTAv2::new_default_meta(accounts(0)).calculate_minimum_deposit(&solo_config(&accounts(1)));
+    assert_eq!(fee.as_yoctonear(), retained.as_yoctonear() / 20);
+    assert!(refund < fee);
+}
+
+#[test]
+fn overpaying_does_not_raise_the_fee() {
+    let quote = |contract: &RTAv2| NearToken::from_yoctonear(contract.quote_minimum_deposit(solo_config(&accounts(1))).0);
+    let (exact_fee, exact_refund) = create_with_fee("s1", quote);
+    let (fee, refund) = create_with_fee("s2", |contract| quote(contract).saturating_add(NearToken::from_near(1)));
+    assert_eq!(fee, exact_fee);
+    assert_eq!(refund, exact_refund.saturating_add(NearToken::from_near(1)));
+}
+
+#[test]
+fn bps_share_rounds_down_without_overflow() {
+    assert_eq!(bps_share(500, 10_000), 500);
+    assert_eq!(bps_share(1, 9_999), 0);
+    assert_eq!(bps_share(10_000, u128::MAX), u128::MAX);
+    assert_eq!(bps_share(2_500, u128::MAX), u128::MAX / 4);
+}
+
+#[test]
+#[should_panic(expected = "Protocol fee of 1001 basis points exceeds the maximum of 1000")]
+fn fee_above_maximum_fails() {
+    set_caller(accounts(0));
+    let mut contract = RTAv2::new_default_meta(accounts(0));
+    contract.set_fee_config(1_001, accounts(4));
+}
+
+#[test]
+#[should_panic(expected = "Only the contract owner can call this method")]
+fn fee_config_is_owner_only() {
+    let mut contract = setup_with_rta("s1", accounts(1));
+    set_caller(accounts(1));
+    contract.set_fee_config(100, accounts(1));
+}
+
+#[test]
+fn create_rta_with_delegate_sets_the_delegation() {
+    set_caller(accounts(0));
+    let mut contract = RTAv2::new_default_meta(accounts(0));
+    set_caller_with_deposit(accounts(1), NearToken::from_near(1));
+    let permissions = vec!["update_chunks".to_string(), "finalize_rta".to_string()];
+    contract.create_rta_with_delegate("s1".to_string(), solo_config(&accounts(1)), accounts(1), accounts(2), permissions);
+    let delegation = contract.get_delegation("s1".to_string(), accounts(2)).unwrap();
+    assert!(delegation.can_update && delegation.can_finalize);
+    assert_eq!(delegation.expires_at, None);
+}
+
+#[test]
+#[should_panic(expected = "receiver_id must be the caller to set a delegate")]
+fn create_rta_with_delegate_for_another_receiver_fails() {
+    set_caller(accounts(0));
+    let mut contract = RTAv2::new_default_meta(accounts(0));
+    set_caller_with_deposit(accounts(3), NearToken::from_near(1));
+    contract.create_rta_with_delegate("s1".to_string(), solo_config(&accounts(1)), accounts(1), accounts(3), update_permissions());
+}
+
+/// Deposit `upgrade_to_group` and `update_config` require to move `rta_id` to `config`.
+fn top_up(contract: &RTAv2, rta_id: &str, config: &RTAConfig) -> NearToken {
+    let current = contract.get_rta_metadata(rta_id.to_string()).unwrap().config;
+    contract.calculate_minimum_deposit(config).saturating_sub(contract.calculate_minimum_deposit(&current))
+}
+
+#[test]
+fn upgrade_to_group_charges_for_the_seats() {
+    let mut contract = setup_with_rta("s1", accounts(1));
+    let config = RTAConfig { ticket_amount: Some(1_000), ..group_config(&accounts(1)) };
+    let required = top_up(&contract, "s1", &config);
+    assert_eq!(required, bytes_cost(1_000 * SEAT_STORAGE_BYTES));
+
+    set_caller_with_deposit(accounts(1), required.saturating_add(NearToken::from_yoctonear(7)));
+    contract.upgrade_to_group("s1".to_string(), 1_000, "1000".to_string());
+    assert_eq!(transferred_to(&accounts(1)), NearToken::from_yoctonear(7));
+    assert_eq!(contract.get_rta_metadata("s1".to_string()).unwrap().config.ticket_amount, Some(1_000));
+}
+
+#[test]
+#[should_panic(expected = "Insufficient deposit")]
+fn upgrade_to_group_without_the_seat_deposit_fails() {
+    let mut contract = setup_with_rta("s1", accounts(1));
+    let config = RTAConfig { ticket_amount: Some(1_000), ..group_config(&accounts(1)) };
+    let required = top_up(&contract, "s1", &config);
+    set_caller_with_deposit(accounts(1), required.saturating_sub(NearToken::from_yoctonear(1)));
+    contract.upgrade_to_group("s1".to_string(), 1_000, "1000".to_string());
+}
+
+#[test]
+#[should_panic(expected = "Insufficient deposit")]
+fn update_config_without_the_chunk_deposit_fails() {
+    let mut contract = setup_with_rta("s1", accounts(1));
+    let config = RTAConfig { expected_chunks: Some(10_000), ..solo_config(&accounts(1)) };
+    assert!(!top_up(&contract, "s1", &config).is_zero());
+    set_caller(accounts(1));
+    contract.update_config("s1".to_string(), config);
+}
+
+#[test]
+fn update_config_lowering_the_deposit_needs_nothing_attached() {
+    let mut contract = setup_with_rta("s1", accounts(1));
+    let config = RTAConfig { expected_chunks: Some(1), ..solo_config(&accounts(1)) };
+    set_caller(accounts(1));
+    contract.update_config("s1".to_string(), config);
+    assert_eq!(contract.get_rta_metadata("s1".to_string()).unwrap().config.expected_chunks, Some(1));
+}
+
+/// Group RTA `g1` owned by `accounts(1)` with one ticket sold to `accounts(2)`.
+fn setup_with_ticket_sold() -> RTAv2 {
+    set_caller(accounts(0));
+    let mut contract = RTAv2::new_default_meta(accounts(0));
+    set_caller_with_deposit(accounts(1), NearToken::from_near(1));
+    contract.create_rta("g1".to_string(), group_config(&accounts(1)), accounts(1));
+    register_storage(&mut contract, accounts(2));
+    set_caller_with_deposit(accounts(2), NearToken::from_yoctonear(1000));
+    contract.buy_ticket("g1".to_string());
+    contract
+}
+
+#[test]
+#[should_panic(expected = "1 tickets already sold")]
+fn update_config_to_solo_after_sales_fails() {
+    let mut contract = setup_with_ticket_sold();
+    set_caller(accounts(1));
+    contract.update_config("g1".to_string(), solo_config(&accounts(1)));
+}
+
+#[test]
+fn update_config_keeps_seats_for_ticket_holders() {
+    let mut contract = setup_with_ticket_sold();
+    set_caller(accounts(1));
+    contract.update_config("g1".to_string(), RTAConfig { ticket_amount: Some(1), ..group_config(&accounts(1)) });
+    assert_eq!(contract.tickets_remaining("g1".to_string()), 0);
+}
@@ -0,0 +1,1204 @@
+// TEE attestation checks shared by the dispatcher and chunker contracts: DCAP collateral and
+// TDX quote verification against the Intel SGX Root CA, and codehash re-derivation
+use hex::{decode, encode};
+use near_sdk::{env, require, serde::Deserialize};
+use p256::ecdsa::{signature::Verifier, Signature, VerifyingKey};
+use serde_json::Value;
+use sha2::{Digest, Sha256, Sha384};
+use x509_cert::{
+    der::{self, asn1::{Any, OctetStringRef}, Decode, DecodePem, Encode, Reader, SliceReader},
+    spki::ObjectIdentifier,
+    Certificate,
+};
+
+// Proof a worker submits alongside its TEE attestation at registration
+#[derive(Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TeeVerificationProof {
+    pub collateral: String, // raw quote collateral JSON, see get_collateral_auto
+    pub quote: String,      // hex TDX quote whose report data starts with sha256(worker account id)
+    pub tcb_info: String,   // raw TCB info JSON from the TEE, with event_log and app_compose
+}
+
+// What a verified TeeVerificationProof attests to
+pub struct Attestation {
+    pub codehash: String,  // image digest re-derived from the measured app compose
+    pub attested_at: u64,  // ns, when Intel issued the collateral
+}
+
+// DCAP quote collateral (V3 layout), as returned by the TEE's quote collateral endpoint.
+// Mirrors dcap_qvl::QuoteCollateralV3 without pulling the verifier into the contract
+pub struct QuoteCollateralV3 {
+    pub tcb_info_issuer_chain: String,
+    pub tcb_info: String,
+    pub tcb_info_signature: Vec<u8>,
+    pub qe_identity_issuer_chain: String,
+    pub qe_identity: String,
+    pub qe_identity_signature: Vec<u8>,
+}
+
+impl QuoteCollateralV3 {
+    // When Intel issued this collateral, in seconds since epoch: the older of the tcb_info and
+    // qe_identity issueDates. Both are covered by the signatures verify_collateral_signatures checks
+    pub fn issued_at_secs(&self) -> u64 {
+        let tcb_info = parse_signed_body(&self.tcb_info, "tcbInfo", "tcb_info");
+        let qe_identity = parse_signed_body(&self.qe_identity, "enclaveIdentity", "qe_identity");
+        let tcb_info_issued = get_timestamp(&tcb_info, "issueDate", "tcb_info");
+        tcb_info_issued.min(get_timestamp(&qe_identity, "issueDate", "qe_identity"))
+    }
+}
+
+// V4 collateral: the V3 fields plus the TCB info v4 structure used by newer TDX stacks
+pub struct QuoteCollateralV4 {
+    pub base: QuoteCollateralV3,
+    pub tcb_info_v4: String,
+    pub tcb_info_v4_signature: Vec<u8>, // by the leaf of base.tcb_info_issuer_chain
+}
+
+pub enum QuoteCollateral {
+    V3(QuoteCollateralV3),
+    V4(QuoteCollateralV4),
+}
+
+impl QuoteCollateral {
+    // The V3 fields every version carries; signature and TCB status checks run on these
+    pub fn base(&self) -> &QuoteCollateralV3 {
+        match self {
+            QuoteCollateral::V3(collateral) => collateral,
+            QuoteCollateral::V4(collateral) => &collateral.base,
+        }
+    }
+
+    // verify_collateral_signatures, plus the V4 TCB info against the same issuer chain as V3's
+    pub fn verify_signatures(&self, now_secs: u64) {
+        self.verify_signatures_with_root(&intel_root_ca(), now_secs);
+    }
+
+    fn verify_signatures_with_root(&self, root: &Certificate, now_secs: u64) {
+        verify_collateral_signatures(self.base(), root, now_secs);
+        if let QuoteCollateral::V4(collateral) = self {
+            verify_signature(
+                &collateral.base.tcb_info_issuer_chain,
+                root,
+                &collateral.tcb_info_v4,
+                &collateral.tcb_info_v4_signature,
+                "tcb_info_v4",
+                now_secs,
+            );
+        }
+    }
+
+    // check_tcb_status for every TCB info the version carries; returns the most specific status
+    pub fn check_tcb_status(&self, platform: &PlatformTcb, now_secs: u64) -> String {
+        let status = check_tcb_status(self.base(), platform, now_secs);
+        match self {
+            QuoteCollateral::V3(_) => status,
+            QuoteCollateral::V4(collateral) => {
+                check_tcb_info_status(&collateral.tcb_info_v4, platform, now_secs)
+            }
+        }
+    }
+}
+
+// P-256 ECDSA signatures are encoded as raw r || s
+const P256_SIGNATURE_LEN: usize = 64;
+
+// Signature algorithm Intel uses for every certificate in its PCS chains
+const ECDSA_WITH_SHA256: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.10045.4.3.2");
+
+// Intel SGX Root CA, valid until 2049-12-31; every collateral issuer chain must end here
+// (SHA-256 fingerprint 44:A0:19:6B:2B:99:F8:89:B8:E1:49:E9:5B:80:7A:35:0E:74:24:96:43:99:E8:85:A7:CB:B8:CC:FA:B6:74:D3)
+const INTEL_SGX_ROOT_CA: &str = "-----BEGIN CERTIFICATE-----
+MIICjzCCAjSgAwIBAgIUImUM1lqdNInzg7SVUr9QGzknBqwwCgYIKoZIzj0EAwIw
+aDEaMBgGA1UEAwwRSW50ZWwgU0dYIFJvb3QgQ0ExGjAYBgNVBAoMEUludGVsIENv
+cnBvcmF0aW9uMRQwEgYDVQQHDAtTYW50YSBDbGFyYTELMAkGA1UECAwCQ0ExCzAJ
+BgNVBAYTAlVTMB4XDTE4MDUyMTEwNDUxMFoXDTQ5MTIzMTIzNTk1OVowaDEaMBgG
+A1UEAwwRSW50ZWwgU0dYIFJvb3QgQ0ExGjAYBgNVBAoMEUludGVsIENvcnBvcmF0
+aW9uMRQwEgYDVQQHDAtTYW50YSBDbGFyYTELMAkGA1UECAwCQ0ExCzAJBgNVBAYT
+AlVTMFkwEwYHKoZIzj0CAQYIKoZIzj0DAQcDQgAEC6nEwMDIYZOj/iPWsCzaEKi7
+1OiOSLRFhWGjbnBVJfVnkY4u3IjkDYYL0MxO4mqsyYjlBalTVYxFP2sJBK5zlKOB
+uzCBuDAfBgNVHSMEGDAWgBQiZQzWWp00ifODtJVSv1AbOScGrDBSBgNVHR8ESzBJ
+MEegRaBDhkFodHRwczovL2NlcnRpZmljYXRlcy50cnVzdGVkc2VydmljZXMuaW50
+ZWwuY29tL0ludGVsU0dYUm9vdENBLmRlcjAdBgNVHQ4EFgQUImUM1lqdNInzg7SV
+Ur9QGzknBqwwDgYDVR0PAQH/BAQDAgEGMBIGA1UdEwEB/wQIMAYBAf8CAQEwCgYI
+KoZIzj0EAwIDSQAwRgIhAOW/5QkR+S9CiSDcNoowLuPRLsWGf/Yi7GSX94BgwTwg
+AiEA4J0lrHoMs+Xo5o/sX6O9QWxHRAvZUGOdRQ7cvqRXaqI=
+-----END CERTIFICATE-----
+";
+
+// TCB statuses a worker may register with; anything else (OutOfDate, Revoked, ...) is rejected
+const ALLOWED_TCB_STATUSES: [&str; 2] = ["UpToDate", "SWHardeningNeeded"];
+
+pub fn get_collateral(raw_quote_collateral: String) -> QuoteCollateralV3 {
+    parse_collateral_v3(&parse_collateral(&raw_quote_collateral))
+}
+
+pub fn get_collateral_v4(raw_quote_collateral: String) -> QuoteCollateralV4 {
+    parse_collateral_v4(&parse_collateral(&raw_quote_collateral))
+}
+
+// Route to the V3 or V4 parser by the collateral's `version` field (V3 when absent)
+pub fn get_collateral_auto(raw_quote_collateral: String) -> QuoteCollateral {
+    let quote_collateral = parse_collateral(&raw_quote_collateral);
+    match quote_collateral.get("version").map(|v| v.as_u64()) {
+        None | Some(Some(3)) => QuoteCollateral::V3(get_collateral(raw_quote_collateral)),
+        Some(Some(4)) => QuoteCollateral::V4(get_collateral_v4(raw_quote_collateral)),
+        Some(_) => env::panic_str("Unsupported quote collateral version"),
+    }
+}
+
+fn parse_collateral(raw_quote_collateral: &str) -> Value {
+    serde_json::from_str(raw_quote_collateral)
+        .unwrap_or_else(|_| env::panic_str("Quote collateral is not valid JSON"))
+}
+
+fn parse_collateral_v3(quote_collateral: &Value) -> QuoteCollateralV3 {
+    let collateral = QuoteCollateralV3 {
+        tcb_info_issuer_chain: get_str(quote_collateral, "tcb_info_issuer_chain"),
+        tcb_info: get_str(quote_collateral, "tcb_info"),
+        tcb_info_signature: get_hex(quote_collateral, "tcb_info_signature"),
+        qe_identity_issuer_chain: get_str(quote_collateral, "qe_identity_issuer_chain"),
+        qe_identity: get_str(quote_collateral, "qe_identity"),
+        qe_identity_signature: get_hex(quote_collateral, "qe_identity_signature"),
+    };
+
+    require!(
+        collateral.tcb_info_signature.len() == P256_SIGNATURE_LEN,
+        "tcb_info_signature must be a 64-byte P-256 signature"
+    );
+    require!(
+        collateral.qe_identity_signature.len() == P256_SIGNATURE_LEN,
+        "qe_identity_signature must be a 64-byte P-256 signature"
+    );
+
+    collateral
+}
+
+fn parse_collateral_v4(quote_collateral: &Value) -> QuoteCollateralV4 {
+    let collateral = QuoteCollateralV4 {
+        base: parse_collateral_v3(quote_collateral),
+        tcb_info_v4: get_str(quote_collateral, "tcb_info_v4"),
+        tcb_info_v4_signature: get_hex(quote_collateral, "tcb_info_v4_signature"),
+    };
+
+    require!(
+        collateral.tcb_info_v4_signature.len() == P256_SIGNATURE_LEN,
+        "tcb_info_v4_signature must be a 64-byte P-256 signature"
+    );
+
+    collateral
+}
+
+// Check tcb_info and qe_identity were signed by the leaf certificate of their issuer chains,
+// and that both chains lead up to `root` (the Intel SGX Root CA outside tests)
+fn verify_collateral_signatures(collateral: &QuoteCollateralV3, root: &Certificate, now_secs: u64) {
+    verify_signature(
+        &collateral.tcb_info_issuer_chain,
+        root,
+        &collateral.tcb_info,
+        &collateral.tcb_info_signature,
+        "tcb_info",
+        now_secs,
+    );
+    verify_signature(
+        &collateral.qe_identity_issuer_chain,
+        root,
+        &collateral.qe_identity,
+        &collateral.qe_identity_signature,
+        "qe_identity",
+        now_secs,
+    );
+}
+
+pub fn intel_root_ca() -> Certificate {
+    Certificate::from_pem(INTEL_SGX_ROOT_CA)
+        .unwrap_or_else(|_| env::panic_str("Intel SGX Root CA is not valid PEM"))
+}
+
+fn verify_signature(
+    issuer_chain: &str,
+    root: &Certificate,
+    payload: &str,
+    signature: &[u8],
+    name: &str,
+    now_secs: u64,
+) {
+    let signing_cert = verify_issuer_chain(issuer_chain, root, name, now_secs);
+    let signature = Signature::from_slice(signature)
+        .unwrap_or_else(|_| env::panic_str(&format!("{} signature is malformed", name)));
+    require!(
+        certificate_key(&signing_cert, name).verify(payload.as_bytes(), &signature).is_ok(),
+        format!("{} signature does not match its issuer chain", name)
+    );
+}
+
+// Leaf certificate of a PEM issuer chain (leaf first), once every certificate in it is
+// within its validity period and signed by the next, and the chain ends at `root`
+pub fn verify_issuer_chain(issuer_chain: &str, root: &Certificate, name: &str, now_secs: u64) -> Certificate {
+    let certs = Certificate::load_pem_chain(issuer_chain.as_bytes())
+        .unwrap_or_else(|_| env::panic_str(&format!("{} issuer chain is not valid PEM", name)));
+    require!(
+        certs.last().is_some_and(|last| last == root),
+        format!("{} issuer chain does not end at the pinned root CA", name)
+    );
+    for cert in &certs {
+        let validity = &cert.tbs_certificate.validity;
+        require!(
+            validity.not_before.to_unix_duration().as_secs() <= now_secs
+                && now_secs <= validity.not_after.to_unix_duration().as_secs(),
+            format!("{} issuer chain has a certificate outside its validity period", name)
+        );
+    }
+    for pair in certs.windows(2) {
+        require!(
+            is_signed_by(&pair[0], &certificate_key(&pair[1], name)),
+            format!("{} issuer chain has a certificate not signed by its issuer", name)
+        );
+    }
+    certs.into_iter().next().unwrap()
+}
+
+fn certificate_key(cert: &Certificate, name: &str) -> VerifyingKey {
+    let public_key = cert.tbs_certificate.subject_public_key_info.subject_public_key.raw_bytes();
+    VerifyingKey::from_sec1_bytes(public_key)
+        .unwrap_or_else(|_| env::panic_str(&format!("{} issuer chain has a non-P-256 key", name)))
+}
+
+fn is_signed_by(cert: &Certificate, issuer_key: &VerifyingKey) -> bool {
+    let (Some(signature), Ok(tbs)) = (cert.signature.as_bytes(), cert.tbs_certificate.to_der()) else {
+        return false;
+    };
+    cert.signature_algorithm.oid == ECDSA_WITH_SHA256
+        && Signature::from_der(signature).is_ok_and(|signature| issuer_key.verify(&tbs, &signature).is_ok())
+}
+
+// TDX quote v4 layout (Intel TDX DCAP Quote Generation Library, appendix A): a 48-byte header
+// and 584-byte TD report, both signed by the attestation key, then the signature data
+const QUOTE_VERSION: u16 = 4;
+const ECDSA_P256_KEY_TYPE: u16 = 2;
+const TDX_TEE_TYPE: u32 = 0x81;
+const QUOTE_HEADER_LEN: usize = 48;
+const TD_REPORT_LEN: usize = 584;
+const TD_REPORT_RTMR0: usize = 328; // four 48-byte RTMRs, then the 64-byte report data
+const TD_REPORT_DATA: usize = 520;
+const QE_REPORT_CERT_DATA: u16 = 6;
+const PCK_CERT_CHAIN_DATA: u16 = 5;
+// SGX report body of the quoting enclave
+const QE_REPORT_LEN: usize = 384;
+const QE_REPORT_MRSIGNER: usize = 128;
+const QE_REPORT_ISVPRODID: usize = 256;
+const QE_REPORT_DATA: usize = 320;
+const TD_REPORT_TEE_TCB_SVN: usize = 0; // 16 bytes
+
+// Intel SGX extension of a PCK certificate (PCK Certificate and CRL Profile, section 1.5)
+const SGX_EXTENSION: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.113741.1.13.1");
+const SGX_TCB: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.113741.1.13.1.2");
+const SGX_PCESVN: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.113741.1.13.1.2.17");
+const SGX_FMSPC: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.113741.1.13.1.4");
+
+// TD report fields of a quote verify_quote has checked
+pub struct TdxQuote {
+    pub rtmrs: [String; 4], // hex RTMR0-3
+    pub report_data: [u8; 64],
+    pub tcb: PlatformTcb,
+}
+
+// The platform's TCB: SGX SVNs and FMSPC as certified in its PCK certificate, and the TDX
+// module's TEE TCB SVN from the TD report
+pub struct PlatformTcb {
+    pub fmspc: String, // hex
+    pub sgx_svns: [u8; 16],
+    pub pcesvn: u16,
+    pub tdx_svns: [u8; 16],
+}
+
+impl PlatformTcb {
+    // Whether every component is at or above a tcb_info level's `tcb`. Levels missing a
+    // component never match
+    fn meets(&self, tcb: &Value) -> bool {
+        let at_least = |svns: &[u8; 16], components: &Value| {
+            components.as_array().is_some_and(|components| {
+                components.len() == svns.len()
+                    && components
+                        .iter()
+                        .zip(svns)
+                        .all(|(component, svn)| component["svn"].as_u64().is_some_and(|min| u64::from(*svn) >= min))
+            })
+        };
+        at_least(&self.sgx_svns, &tcb["sgxtcbcomponents"])
+            && tcb["pcesvn"].as_u64().is_some_and(|min| u64::from(self.pcesvn) >= min)
+            && at_least(&self.tdx_svns, &tcb["tdxtcbcomponents"])
+    }
+}
+
+// Check a TDX quote end to end: the attestation key signed the header and TD report, the
+// quoting enclave's report commits to that key, a PCK certificate chaining to the Intel SGX
+// Root CA signed the QE report, and the QE is the one Intel's signed qe_identity names
+pub fn verify_quote(quote: &[u8], collateral: &QuoteCollateralV3, now_secs: u64) -> TdxQuote {
+    verify_quote_with_root(quote, collateral, &intel_root_ca(), now_secs)
+}
+
+fn verify_quote_with_root(
+    quote: &[u8],
+    collateral: &QuoteCollateralV3,
+    root: &Certificate,
+    now_secs: u64,
+) -> TdxQuote {
+    let mut reader = QuoteReader(quote);
+    let header = reader.take(QUOTE_HEADER_LEN);
+    require!(le_u16(&header[0..2]) == QUOTE_VERSION, "Unsupported quote version");
+    require!(le_u16(&header[2..4]) == ECDSA_P256_KEY_TYPE, "Quote attestation key is not P-256");
+    require!(le_u32(&header[4..8]) == TDX_TEE_TYPE, "Quote is not a TDX quote");
+    let td_report = reader.take(TD_REPORT_LEN);
+
+    let signature_data_len = reader.u32() as usize;
+    let mut signature_data = QuoteReader(reader.take(signature_data_len));
+    let quote_signature = signature_data.take(P256_SIGNATURE_LEN);
+    let attestation_key = signature_data.take(64);
+    require!(signature_data.u16() == QE_REPORT_CERT_DATA, "Quote certification data is not a QE report");
+    let qe_data_len = signature_data.u32() as usize;
+    let mut qe_data = QuoteReader(signature_data.take(qe_data_len));
+    let qe_report = qe_data.take(QE_REPORT_LEN);
+    let qe_report_signature = qe_data.take(P256_SIGNATURE_LEN);
+    let qe_auth_data_len = qe_data.u16() as usize;
+    let qe_auth_data = qe_data.take(qe_auth_data_len);
+    require!(qe_data.u16() == PCK_CERT_CHAIN_DATA, "QE certification data is not a PCK certificate chain");
+    let pck_chain_len = qe_data.u32() as usize;
+    let pck_chain = qe_data.take(pck_chain_len);
+
+    let attestation_key = VerifyingKey::from_sec1_bytes(&[&[0x04], attestation_key].concat())
+        .unwrap_or_else(|_| env::panic_str("Quote attestation key is not a P-256 key"));
+    require!(
+        verify_raw(&attestation_key, &quote[..QUOTE_HEADER_LEN + TD_REPORT_LEN], quote_signature),
+        "Quote signature does not match its attestation key"
+    );
+
+    // The QE report data is sha256(attestation key || QE auth data), zero padded
+    let key_hash = Sha256::new().chain_update(&attestation_key.to_encoded_point(false).as_bytes()[1..]);
+    let key_hash = key_hash.chain_update(qe_auth_data).finalize();
+    let qe_report_data = &qe_report[QE_REPORT_DATA..];
+    require!(
+        qe_report_data[..32] == key_hash[..] && qe_report_data[32..].iter().all(|b| *b == 0),
+        "QE report does not commit to the attestation key"
+    );
+
+    let pck_chain = std::str::from_utf8(pck_chain)
+        .unwrap_or_else(|_| env::panic_str("PCK issuer chain is not valid PEM"))
+        .trim_end_matches('\0');
+    let pck_cert = verify_issuer_chain(pck_chain, root, "PCK", now_secs);
+    require!(
+        verify_raw(&certificate_key(&pck_cert, "PCK"), qe_report, qe_report_signature),
+        "QE report signature does not match the PCK certificate"
+    );
+    check_qe_identity(&collateral.qe_identity, qe_report);
+
+    let rtmr = |index: usize| {
+        let start = TD_REPORT_RTMR0 + index * 48;
+        encode(&td_report[start..start + 48])
+    };
+    let (sgx_svns, pcesvn, fmspc) = pck_tcb(&pck_cert);
+    TdxQuote {
+        rtmrs: [rtmr(0), rtmr(1), rtmr(2), rtmr(3)],
+        report_data: td_report[TD_REPORT_DATA..].try_into().unwrap(),
+        tcb: PlatformTcb {
+            fmspc,
+            sgx_svns,
+            pcesvn,
+            tdx_svns: td_report[TD_REPORT_TEE_TCB_SVN..TD_REPORT_TEE_TCB_SVN + 16].try_into().unwrap(),
+        },
+    }
+}
+
+// SGX component SVNs, PCESVN and hex FMSPC from the PCK certificate's SGX extension
+fn pck_tcb(pck_cert: &Certificate) -> ([u8; 16], u16, String) {
+    fn malformed() -> ! {
+        env::panic_str("PCK certificate SGX extension is malformed")
+    }
+    let extension = pck_cert
+        .tbs_certificate
+        .extensions
+        .iter()
+        .flatten()
+        .find(|extension| extension.extn_id == SGX_EXTENSION)
+        .unwrap_or_else(|| env::panic_str("PCK certificate has no SGX extension"));
+    let entries = sgx_entries(extension.extn_value.as_bytes()).unwrap_or_else(|_| malformed());
+    let entry = |oid: ObjectIdentifier| entries.iter().find(|(id, _)| *id == oid).map(|(_, value)| value);
+
+    let tcb = entry(SGX_TCB)
+        .and_then(|tcb| sgx_entries(tcb.to_der().ok()?.as_slice()).ok())
+        .unwrap_or_else(|| malformed());
+    let mut sgx_svns = [0u8; 16];
+    let mut pcesvn = None;
+    for (id, value) in &tcb {
+        if *id == SGX_PCESVN {
+            pcesvn = value.decode_as::<u16>().ok();
+        } else if id.parent() == Some(SGX_TCB) {
+            // sgxTcbComp01Svn .. sgxTcbComp16Svn are arcs 1-16
+            let index = id.arcs().last().and_then(|arc| usize::try_from(arc).ok());
+            if let Some(svn) = index.filter(|i| (1..=16).contains(i)).and_then(|i| sgx_svns.get_mut(i - 1)) {
+                *svn = value.decode_as::<u8>().unwrap_or_else(|_| malformed());
+            }
+        }
+    }
+    let fmspc = entry(SGX_FMSPC)
+        .and_then(|fmspc| fmspc.decode_as::<OctetStringRef>().ok())
+        .unwrap_or_else(|| malformed());
+    (sgx_svns, pcesvn.unwrap_or_else(|| malformed()), encode(fmspc.as_bytes()))
+}
+
+// The SGX extension and its TCB entry are both SEQUENCE OF SEQUENCE { OID, value }
+fn sgx_entries(der: &[u8]) -> der::Result<Vec<(ObjectIdentifier, Any)>> {
+    Vec::<Any>::from_der(der)?
+        .iter()
+        .map(|entry| {
+            let mut reader = SliceReader::new(entry.value())?;
+            Ok((reader.decode()?, reader.decode()?))
+        })
+        .collect()
+}
+
+// The QE that produced the report must have the MRSIGNER and product ID Intel publishes
+fn check_qe_identity(raw_qe_identity: &str, qe_report: &[u8]) {
+    let qe_identity = parse_signed_body(raw_qe_identity, "enclaveIdentity", "qe_identity");
+    let mrsigner = qe_identity["mrsigner"]
+        .as_str()
+        .and_then(|mrsigner| decode(mrsigner).ok())
+        .unwrap_or_else(|| env::panic_str("qe_identity mrsigner missing"));
+    let isvprodid = qe_identity["isvprodid"]
+        .as_u64()
+        .unwrap_or_else(|| env::panic_str("qe_identity isvprodid missing"));
+    require!(
+        qe_report[QE_REPORT_MRSIGNER..QE_REPORT_MRSIGNER + 32] == mrsigner[..]
+            && u64::from(le_u16(&qe_report[QE_REPORT_ISVPRODID..])) == isvprodid,
+        "QE report does not match qe_identity"
+    );
+}
+
+fn verify_raw(key: &VerifyingKey, message: &[u8], signature: &[u8]) -> bool {
+    Signature::from_slice(signature).is_ok_and(|signature| key.verify(message, &signature).is_ok())
+}
+
+fn le_u16(bytes: &[u8]) -> u16 {
+    u16::from_le_bytes([bytes[0], bytes[1]])
+}
+
+fn le_u32(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+// Cursor over a quote's length-prefixed, little-endian sections
+struct QuoteReader<'a>(&'a [u8]);
+
+impl<'a> QuoteReader<'a> {
+    fn take(&mut self, len: usize) -> &'a [u8] {
+        require!(len <= self.0.len(), "Quote is truncated");
+        let (head, rest) = self.0.split_at(len);
+        self.0 = rest;
+        head
+    }
+
+    fn u16(&mut self) -> u16 {
+        le_u16(self.take(2))
+    }
+
+    fn u32(&mut self) -> u32 {
+        le_u32(self.take(4))
+    }
+}
+
+// Reject TCB info past its nextUpdate, for another FMSPC, or placing `platform` at a status
+// outside ALLOWED_TCB_STATUSES. Returns the status so callers can log it
+pub fn check_tcb_status(collateral: &QuoteCollateralV3, platform: &PlatformTcb, now_secs: u64) -> String {
+    check_tcb_info_status(&collateral.tcb_info, platform, now_secs)
+}
+
+fn check_tcb_info_status(raw_tcb_info: &str, platform: &PlatformTcb, now_secs: u64) -> String {
+    let tcb_info = parse_signed_body(raw_tcb_info, "tcbInfo", "tcb_info");
+    require!(
+        now_secs <= get_timestamp(&tcb_info, "nextUpdate", "tcb_info"),
+        "TCB info has expired"
+    );
+    require!(
+        tcb_info["fmspc"].as_str().is_some_and(|fmspc| fmspc.eq_ignore_ascii_case(&platform.fmspc)),
+        "tcb_info is for a different FMSPC than the PCK certificate"
+    );
+
+    // Intel lists tcbLevels newest first; the platform is at the first level it meets
+    let status = tcb_info["tcbLevels"]
+        .as_array()
+        .unwrap_or_else(|| env::panic_str("tcb_info tcbLevels missing"))
+        .iter()
+        .find(|level| platform.meets(&level["tcb"]))
+        .unwrap_or_else(|| env::panic_str("Platform TCB is below every tcb_info level"))["tcbStatus"]
+        .as_str()
+        .unwrap_or_else(|| env::panic_str("tcb_info tcbStatus missing"));
+    require!(
+        ALLOWED_TCB_STATUSES.contains(&status),
+        format!("TCB status {} is not allowed", status)
+    );
+    status.to_owned()
+}
+
+// Intel serves signed collateral as {"<body_key>": {...}, "signature": ...}; accept the bare body too
+fn parse_signed_body(raw: &str, body_key: &str, name: &str) -> Value {
+    let value: Value = serde_json::from_str(raw)
+        .unwrap_or_else(|_| env::panic_str(&format!("{} is not valid JSON", name)));
+    match value.get(body_key) {
+        Some(body) => body.clone(),
+        None => value,
+    }
+}
+
+fn get_timestamp(body: &Value, key: &str, name: &str) -> u64 {
+    let timestamp = body[key]
+        .as_str()
+        .unwrap_or_else(|| env::panic_str(&format!("{} {} missing", name, key)));
+    parse_rfc3339_secs(timestamp)
+        .unwrap_or_else(|| env::panic_str(&format!("{} {} is not an RFC 3339 UTC timestamp", name, key)))
+}
+
+// Seconds since epoch for "YYYY-MM-DDTHH:MM:SSZ" (fractional seconds are ignored).
+// None for out-of-range fields such as month 13 or February 30
+fn parse_rfc3339_secs(timestamp: &str) -> Option<u64> {
+    let (date, time) = timestamp.strip_suffix('Z')?.split_once('T')?;
+    let mut date = date.splitn(3, '-').map(|p| p.parse::<i64>().ok());
+    let (year, month, day) = (date.next()??, date.next()??, date.next()??);
+    let time = time.split('.').next()?;
+    let mut time = time.splitn(3, ':').map(|p| p.parse::<i64>().ok());
+    let (hour, minute, second) = (time.next()??, time.next()??, time.next()??);
+    // RFC 3339 allows second 60 for leap seconds
+    if !(1..=12).contains(&month)
+        || !(1..=days_in_month(year, month)).contains(&day)
+        || !(0..24).contains(&hour)
+        || !(0..60).contains(&minute)
+        || !(0..=60).contains(&second)
+    {
+        return None;
+    }
+
+    // Days from civil date (Howard Hinnant's algorithm)
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let doy = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+
+    u64::try_from(days * 86_400 + hour * 3_600 + minute * 60 + second).ok()
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+fn get_str(value: &Value, key: &str) -> String {
+    value[key]
+        .as_str()
+        .unwrap_or_else(|| env::panic_str(&format!("Quote collateral missing {}", key)))
+        .to_owned()
+}
+
+fn get_hex(value: &Value, key: &str) -> Vec<u8> {
+    decode(get_str(value, key))
+        .unwrap_or_else(|_| env::panic_str(&format!("Quote collateral {} is not hex", key)))
+}
+
+// Full registration check for `worker_account_id`: the collateral and quote must chain to Intel,
+// the quote must be bound to the account, the collateral no older than `max_age_ns`, and every
+// RTMR must replay from the TCB info. `raw_proof` is a JSON TeeVerificationProof
+pub fn verify_tee_proof(
+    raw_proof: &str,
+    worker_account_id: &str,
+    max_age_ns: u64,
+    is_compose_hash_approved: impl Fn(&str) -> bool,
+) -> Attestation {
+    let proof: TeeVerificationProof = serde_json::from_str(raw_proof)
+        .unwrap_or_else(|_| env::panic_str("Invalid TEE verification proof"));
+    let quote_collateral = get_collateral_auto(proof.collateral);
+    let now = env::block_timestamp();
+    quote_collateral.verify_signatures(now / 1_000_000_000);
+    let quote = decode(&proof.quote).unwrap_or_else(|_| env::panic_str("Quote is not hex"));
+    let quote = verify_quote(&quote, quote_collateral.base(), now / 1_000_000_000);
+    // Quotes are generated for one account, so another worker can't replay them
+    require!(
+        quote.report_data[..32] == env::sha256(worker_account_id.as_bytes())[..],
+        "Quote report data does not match the worker account"
+    );
+    let attested_at = quote_collateral.base().issued_at_secs().saturating_mul(1_000_000_000);
+    let tcb_status = quote_collateral.check_tcb_status(&quote.tcb, now / 1_000_000_000);
+    env::log_str(&format!("Worker TCB status: {}", tcb_status));
+    require!(attested_at <= now, "Attestation is from the future");
+    require!(now - attested_at <= max_age_ns, "Attestation is too old");
+
+    let [rtmr0, rtmr1, rtmr2, rtmr3] = quote.rtmrs;
+    verify_rtmrs(&proof.tcb_info, &rtmr0, &rtmr1, &rtmr2, &rtmr3);
+    let codehash = verify_codehash(proof.tcb_info, rtmr3, is_compose_hash_approved);
+    Attestation { codehash, attested_at }
+}
+
+// Re-derive the app codehash from the TEE's TCB info, checking it against the quote's RTMR3.
+// is_compose_hash_approved can pin the whole compose manifest (env, mounts, secrets), not just
+// the image digest. Malformed TCB info panics with a message naming the missing or
+// invalid field
+pub fn verify_codehash(
+    raw_tcb_info: String,
+    rtmr3: String,
+    is_compose_hash_approved: impl Fn(&str) -> bool,
+) -> String {
+    let tcb_info = parse_tcb_info(&raw_tcb_info);
+    let event_log = get_event_log(&tcb_info);
+    let app_compose = tcb_info["app_compose"]
+        .as_str()
+        .unwrap_or_else(|| env::panic_str("app_compose missing"));
+
+    // The compose-hash event commits to the app compose file, and RTMR3 to the event log
+    let compose_hash_event = event_log
+        .iter()
+        .find(|e| e["event"].as_str() == Some("compose-hash"))
+        .unwrap_or_else(|| env::panic_str("no compose-hash event"));
+    let compose_hash = compose_hash_event["event_payload"]
+        .as_str()
+        .unwrap_or_else(|| env::panic_str("compose-hash event_payload missing"));
+    require!(
+        replay_app_compose(app_compose) == compose_hash,
+        "app_compose does not match compose-hash event"
+    );
+    require_rtmr(event_log, 3, &rtmr3);
+    require!(
+        is_compose_hash_approved(compose_hash),
+        "Compose hash not approved"
+    );
+
+    // The codehash is the image digest every service in the compose file pins
+    image_digest(app_compose)
+}
+
+// Image digest of the app compose's docker_compose_file, parsed as YAML. Every service must
+// pin its image by digest, and all of them must pin the same one, so the codehash covers
+// whatever the compose runs
+fn image_digest(app_compose: &str) -> String {
+    let app_compose: Value = serde_json::from_str(app_compose)
+        .unwrap_or_else(|_| env::panic_str("app_compose is not valid JSON"));
+    let compose_file = app_compose["docker_compose_file"]
+        .as_str()
+        .unwrap_or_else(|| env::panic_str("app_compose docker_compose_file missing"));
+    let compose_file: serde_yaml::Value = serde_yaml::from_str(compose_file)
+        .unwrap_or_else(|_| env::panic_str("app_compose docker_compose_file is not valid YAML"));
+    let services = compose_file["services"]
+        .as_mapping()
+        .unwrap_or_else(|| env::panic_str("app_compose has no services"));
+
+    let mut codehash = None;
+    for service in services.values() {
+        let image = service["image"]
+            .as_str()
+            .unwrap_or_else(|| env::panic_str("app_compose service has no image"));
+        let (_, digest) = image
+            .split_once("@sha256:")
+            .unwrap_or_else(|| env::panic_str("app_compose image has no @sha256: digest"));
+        require!(
+            codehash.get_or_insert(digest) == &digest,
+            "app_compose images have conflicting digests"
+        );
+    }
+    codehash
+        .unwrap_or_else(|| env::panic_str("app_compose has no image"))
+        .to_owned()
+}
+
+// Full measured-boot check: replay every IMR's events and require all four RTMRs to match the quote
+pub fn verify_rtmrs(raw_tcb_info: &str, rtmr0: &str, rtmr1: &str, rtmr2: &str, rtmr3: &str) {
+    let tcb_info = parse_tcb_info(raw_tcb_info);
+    let event_log = get_event_log(&tcb_info);
+    for (imr, rtmr) in [rtmr0, rtmr1, rtmr2, rtmr3].into_iter().enumerate() {
+        require_rtmr(event_log, imr as u8, rtmr);
+    }
+}
+
+fn parse_tcb_info(raw_tcb_info: &str) -> Value {
+    serde_json::from_str(raw_tcb_info).unwrap_or_else(|_| env::panic_str("TCB info is not valid JSON"))
+}
+
+fn get_event_log(tcb_info: &Value) -> &Vec<Value> {
+    tcb_info["event_log"]
+        .as_array()
+        .unwrap_or_else(|| env::panic_str("event_log missing"))
+}
+
+fn require_rtmr(event_log: &[Value], imr: u8, rtmr: &str) {
+    if replay_rtmr(event_log, imr) != rtmr {
+        env::panic_str(&format!("RTMR{} mismatch", imr));
+    }
+}
+
+fn replay_app_compose(app_compose: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(app_compose);
+    encode(hasher.finalize())
+}
+
+fn replay_rtmr(event_log: &[Value], imr: u8) -> String {
+    let mut digest = [0u8; 48];
+    let filtered_events = event_log.iter().filter(|e| {
+        e["imr"].as_u64().unwrap_or_else(|| env::panic_str("event_log entry imr missing")) as u8 == imr
+    });
+    for event in filtered_events {
+        let event_digest = event["digest"]
+            .as_str()
+            .unwrap_or_else(|| env::panic_str("event_log entry digest missing"));
+        let event_digest = decode(event_digest)
+            .unwrap_or_else(|_| env::panic_str("event_log entry digest is not hex"));
+        let mut hasher = Sha384::new();
+        hasher.update(digest);
+        hasher.update(event_digest.as_slice());
+        digest = hasher.finalize().into();
+    }
+    encode(digest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Throwaway P-256 test PKI: a root, a leaf signed by it (valid 2020-2030), the same leaf
+    // key in a certificate that expired in 2021, and a self-signed certificate for that key
+    const TEST_ROOT: &str = "-----BEGIN CERTIFICATE-----
+MIIBMjCB2aADAgECAgID9DAKBggqhkjOPQQDAjAXMRUwEwYDVQQDDAxUZXN0IFJv
+b3QgQ0EwIBcNMjAwMTAxMDAwMDAwWhgPMjA1MDAxMDEwMDAwMDBaMBcxFTATBgNV
+BAMMDFRlc3QgUm9vdCBDQTBZMBMGByqGSM49AgEGCCqGSM49AwEHA0IABA3MdkjH
+ijEY8mEoZv6D7xn0AwT2IzmeEhGhDys9HAXPMOgCdne8uRKUPpn/cRQOqbwkcTvh
+t1wx/0BUckxPnQCjEzARMA8GA1UdEwEB/wQFMAMBAf8wCgYIKoZIzj0EAwIDSAAw
+RQIhAPz63614JKJ94llNg3NsC7TUXU7GaqgWzxoEbLRVt4rrAiBTRWYzBzy+HOD3
+lLezRpGYqwYEvU4YP+4qb6lYpUaxxA==
+-----END CERTIFICATE-----
+";
+    const TEST_LEAF: &str = "-----BEGIN CERTIFICATE-----
+MIIBLDCB1KADAgECAgID9DAKBggqhkjOPQQDAjAXMRUwEwYDVQQDDAxUZXN0IFJv
+b3QgQ0EwHhcNMjAwMTAxMDAwMDAwWhcNMzAwMTAxMDAwMDAwWjAXMRUwEwYDVQQD
+DAxUZXN0IFNpZ25pbmcwWTATBgcqhkjOPQIBBggqhkjOPQMBBwNCAASmLwSPNnNZ
+gJwtRsIEnX178mjDwHPEcnU8sYokqK0gscrM+BBLZmeVx/NdrJ3ERLPCxhl4GYxJ
+hZlVuZlW2l7boxAwDjAMBgNVHRMBAf8EAjAAMAoGCCqGSM49BAMCA0cAMEQCICqU
+/uSCq4XSDe3iFwsymPF9XWL/SQnSpTVNScJVLDh2AiAycSOuSPRO7pcecMzEXdX0
+SCi1g+MudTOJgiDThkbwPg==
+-----END CERTIFICATE-----
+";
+    const TEST_EXPIRED_LEAF: &str = "-----BEGIN CERTIFICATE-----
+MIIBMTCB2KADAgECAgID+DAKBggqhkjOPQQDAjAXMRUwEwYDVQQDDAxUZXN0IFJv
+b3QgQ0EwHhcNMjAwMTAxMDAwMDAwWhcNMjEwMTAxMDAwMDAwWjAbMRkwFwYDVQQD
+DBBUZXN0IFNpZ25pbmcgT2xkMFkwEwYHKoZIzj0CAQYIKoZIzj0DAQcDQgAEpi8E
+jzZzWYCcLUbCBJ19e/Jow8BzxHJ1PLGKJKitILHKzPgQS2ZnlcfzXaydxESzwsYZ
+eBmMSYWZVbmZVtpe26MQMA4wDAYDVR0TAQH/BAIwADAKBggqhkjOPQQDAgNIADBF
+AiEAtKbYWD72DNDZnbsBj9j0fxHq8XqCS+TIPa9Ce26UP3ICIG2zX0hE+XgyiJLS
+e5hKFpnUB8XVlh486lycRcjG/mBT
+-----END CERTIFICATE-----
+";
+    const TEST_SELF_SIGNED: &str = "-----BEGIN CERTIFICATE-----
+MIIBMzCB2aADAgECAgID9DAKBggqhkjOPQQDAjAXMRUwEwYDVQQDDAxUZXN0IFNp
+Z25pbmcwIBcNMjAwMTAxMDAwMDAwWhgPMjA1MDAxMDEwMDAwMDBaMBcxFTATBgNV
+BAMMDFRlc3QgU2lnbmluZzBZMBMGByqGSM49AgEGCCqGSM49AwEHA0IABKYvBI82
+c1mAnC1GwgSdfXvyaMPAc8RydTyxiiSorSCxysz4EEtmZ5XH812sncREs8LGGXgZ
+jEmFmVW5mVbaXtujEzARMA8GA1UdEwEB/wQFMAMBAf8wCgYIKoZIzj0EAwIDSQAw
+RgIhANlyoSkrfwgDH6MRn5Qk//1VU0DcSfgAEBiUk+FI4N8eAiEAmy7ieJKZ5xhX
+Y/hpoRYSMvqIEF0wmd70Uqbodxr2oi4=
+-----END CERTIFICATE-----
+";
+
+    // The leaf key again, as a PCK certificate whose SGX extension certifies FMSPC 00806f050000,
+    // every SGX component SVN at 3 and PCESVN 11
+    const TEST_PCK: &str = "-----BEGIN CERTIFICATE-----
+MIIC9DCCApqgAwIBAgICA/AwCgYIKoZIzj0EAwIwFzEVMBMGA1UEAwwMVGVzdCBS
+b290IENBMB4XDTIwMDEwMTAwMDAwMFoXDTMwMDEwMTAwMDAwMFowEzERMA8GA1UE
+AwwIVGVzdCBQQ0swWTATBgcqhkjOPQIBBggqhkjOPQMBBwNCAASmLwSPNnNZgJwt
+RsIEnX178mjDwHPEcnU8sYokqK0gscrM+BBLZmeVx/NdrJ3ERLPCxhl4GYxJhZlV
+uZlW2l7bo4IB2DCCAdQwDAYDVR0TAQH/BAIwADCCAcIGCSqGSIb4TQENAQSCAbMw
+ggGvMB4GCiqGSIb4TQENAQEEEAAAAAAAAAAAAAAAAAAAAAAwggFjBgoqhkiG+E0B
+DQECMIIBUzAQBgsqhkiG+E0BDQECAQIBAzAQBgsqhkiG+E0BDQECAgIBAzAQBgsq
+hkiG+E0BDQECAwIBAzAQBgsqhkiG+E0BDQECBAIBAzAQBgsqhkiG+E0BDQECBQIB
+AzAQBgsqhkiG+E0BDQECBgIBAzAQBgsqhkiG+E0BDQECBwIBAzAQBgsqhkiG+E0B
+DQECCAIBAzAQBgsqhkiG+E0BDQECCQIBAzAQBgsqhkiG+E0BDQECCgIBAzAQBgsq
+hkiG+E0BDQECCwIBAzAQBgsqhkiG+E0BDQECDAIBAzAQBgsqhkiG+E0BDQECDQIB
+AzAQBgsqhkiG+E0BDQECDgIBAzAQBgsqhkiG+E0BDQECDwIBAzAQBgsqhkiG+E0B
+DQECEAIBAzAQBgsqhkiG+E0BDQECEQIBCzAfBgsqhkiG+E0BDQECEgQQAwMDAwMD
+AwMDAwMDAwMDAzAQBgoqhkiG+E0BDQEDBAIAADAUBgoqhkiG+E0BDQEEBAYAgG8F
+AAAwCgYIKoZIzj0EAwIDSAAwRQIhAOWxjzgbr44JT0V2oTdEPSOxXKpEd1BkcLGt
+m4UUnnhXAiA4qpgReCoVYMIfB3IsPJHpSWEJKP3oyo9NgXVpWOzUAQ==
+-----END CERTIFICATE-----
+";
+
+    // 2025-01-01T00:00:00Z
+    const NOW_SECS: u64 = 1_735_689_600;
+
+    // The test leaf's key is 0x2222; 0x3333 stands in for a quote's attestation key
+    fn sign(key: u16, message: &[u8]) -> Vec<u8> {
+        let mut secret = [0u8; 32];
+        secret[30..].copy_from_slice(&key.to_be_bytes());
+        let signing_key = p256::ecdsa::SigningKey::from_slice(&secret).unwrap();
+        let signature: Signature = p256::ecdsa::signature::Signer::sign(&signing_key, message);
+        signature.to_bytes().to_vec()
+    }
+
+    fn public_key(key: u16) -> Vec<u8> {
+        let mut secret = [0u8; 32];
+        secret[30..].copy_from_slice(&key.to_be_bytes());
+        let signing_key = p256::ecdsa::SigningKey::from_slice(&secret).unwrap();
+        signing_key.verifying_key().to_encoded_point(false).as_bytes()[1..].to_vec()
+    }
+
+    fn leaf_signature(payload: &str) -> Vec<u8> {
+        sign(0x2222, payload.as_bytes())
+    }
+
+    fn test_root() -> Certificate {
+        Certificate::from_pem(TEST_ROOT).unwrap()
+    }
+
+    #[test]
+    fn intel_root_ca_is_self_signed() {
+        let root = intel_root_ca();
+        assert!(is_signed_by(&root, &certificate_key(&root, "root")));
+    }
+
+    #[test]
+    fn accepts_a_chain_ending_at_the_root() {
+        let chain = [TEST_LEAF, TEST_ROOT].concat();
+        verify_signature(&chain, &test_root(), "{}", &leaf_signature("{}"), "tcb_info", NOW_SECS);
+    }
+
+    #[test]
+    #[should_panic(expected = "tcb_info issuer chain does not end at the pinned root CA")]
+    fn rejects_a_self_signed_chain() {
+        verify_signature(TEST_SELF_SIGNED, &intel_root_ca(), "{}", &leaf_signature("{}"), "tcb_info", NOW_SECS);
+    }
+
+    #[test]
+    #[should_panic(expected = "tcb_info issuer chain has a certificate not signed by its issuer")]
+    fn rejects_a_chain_with_a_forged_link() {
+        let chain = [TEST_SELF_SIGNED, INTEL_SGX_ROOT_CA].concat();
+        verify_signature(&chain, &intel_root_ca(), "{}", &leaf_signature("{}"), "tcb_info", NOW_SECS);
+    }
+
+    #[test]
+    #[should_panic(expected = "tcb_info issuer chain has a certificate outside its validity period")]
+    fn rejects_an_expired_certificate() {
+        let chain = [TEST_EXPIRED_LEAF, TEST_ROOT].concat();
+        verify_signature(&chain, &test_root(), "{}", &leaf_signature("{}"), "tcb_info", NOW_SECS);
+    }
+
+    #[test]
+    #[should_panic(expected = "tcb_info signature does not match its issuer chain")]
+    fn rejects_a_payload_the_leaf_did_not_sign() {
+        let chain = [TEST_LEAF, TEST_ROOT].concat();
+        verify_signature(&chain, &test_root(), "{\"tcbInfo\":1}", &leaf_signature("{}"), "tcb_info", NOW_SECS);
+    }
+
+    fn app_compose(compose_file: &str) -> String {
+        serde_json::json!({ "docker_compose_file": compose_file }).to_string()
+    }
+
+    #[test]
+    fn image_digest_ignores_indentation() {
+        for indent in [" ", "  ", "    "] {
+            let compose_file = format!("services:\n{0}app:\n{0}{0}image: repo/app@sha256:abc123\n", indent);
+            assert_eq!(image_digest(&app_compose(&compose_file)), "abc123", "{:?}", indent);
+        }
+    }
+
+    #[test]
+    fn image_digest_handles_crlf_and_cr_line_endings() {
+        for newline in ["\n", "\r\n", "\r"] {
+            let compose_file = ["services:", "  app:", "    image: \"repo/app@sha256:abc123\"", ""].join(newline);
+            assert_eq!(image_digest(&app_compose(&compose_file)), "abc123", "{:?}", newline);
+        }
+    }
+
+    #[test]
+    fn image_digest_only_reads_service_images() {
+        let compose_file = "\
+# image: repo/evil@sha256:bad
+services:
+  app:
+    environment:
+      NOTE: |
+        image: repo/evil@sha256:bad
+    image: repo/app@sha256:abc123
+  sidecar:
+    image: repo/app@sha256:abc123
+";
+        assert_eq!(image_digest(&app_compose(compose_file)), "abc123");
+    }
+
+    #[test]
+    #[should_panic(expected = "app_compose images have conflicting digests")]
+    fn image_digest_rejects_conflicting_digests() {
+        let compose_file = "services:\n  app:\n    image: repo/app@sha256:abc123\n  other:\n    image: repo/other@sha256:def456\n";
+        image_digest(&app_compose(compose_file));
+    }
+
+    #[test]
+    #[should_panic(expected = "app_compose service has no image")]
+    fn image_digest_requires_every_service_to_have_an_image() {
+        image_digest(&app_compose("services:\r\n  app:\r\n    build: .\r\n"));
+    }
+
+    #[test]
+    #[should_panic(expected = "app_compose has no image")]
+    fn image_digest_requires_an_image() {
+        image_digest(&app_compose("services: {}\n"));
+    }
+
+    fn collateral_issued(tcb_issue_date: &str, qe_issue_date: &str) -> QuoteCollateralV3 {
+        QuoteCollateralV3 {
+            tcb_info_issuer_chain: String::new(),
+            tcb_info: format!(r#"{{"issueDate":"{}","nextUpdate":"2030-01-01T00:00:00Z"}}"#, tcb_issue_date),
+            tcb_info_signature: Vec::new(),
+            qe_identity_issuer_chain: String::new(),
+            qe_identity: format!(r#"{{"enclaveIdentity":{{"issueDate":"{}"}}}}"#, qe_issue_date),
+            qe_identity_signature: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn issued_at_is_the_older_signed_issue_date() {
+        let collateral = collateral_issued("2025-01-02T00:00:00Z", "2025-01-01T00:00:00Z");
+        assert_eq!(collateral.issued_at_secs(), NOW_SECS);
+        let collateral = collateral_issued("2025-01-01T00:00:00Z", "2025-01-03T00:00:00Z");
+        assert_eq!(collateral.issued_at_secs(), NOW_SECS);
+    }
+
+    #[test]
+    #[should_panic(expected = "qe_identity issueDate missing")]
+    fn issued_at_requires_both_issue_dates() {
+        let mut collateral = collateral_issued("2025-01-01T00:00:00Z", "2025-01-01T00:00:00Z");
+        collateral.qe_identity = "{}".to_string();
+        collateral.issued_at_secs();
+    }
+
+    // V4 collateral whose every document the test leaf signed, under the test chain
+    fn signed_collateral_v4() -> QuoteCollateral {
+        let chain = [TEST_LEAF, TEST_ROOT].concat();
+        let base = QuoteCollateralV3 {
+            tcb_info_issuer_chain: chain.clone(),
+            tcb_info: "{}".to_string(),
+            tcb_info_signature: leaf_signature("{}"),
+            qe_identity_issuer_chain: chain,
+            qe_identity: "{}".to_string(),
+            qe_identity_signature: leaf_signature("{}"),
+        };
+        let tcb_info_v4 = r#"{"tcbInfo":{"version":4}}"#.to_string();
+        let tcb_info_v4_signature = leaf_signature(&tcb_info_v4);
+        QuoteCollateral::V4(QuoteCollateralV4 { base, tcb_info_v4, tcb_info_v4_signature })
+    }
+
+    #[test]
+    fn accepts_signed_v4_collateral() {
+        signed_collateral_v4().verify_signatures_with_root(&test_root(), NOW_SECS);
+    }
+
+    #[test]
+    #[should_panic(expected = "tcb_info_v4 signature does not match its issuer chain")]
+    fn rejects_unsigned_v4_tcb_info() {
+        let mut collateral = signed_collateral_v4();
+        if let QuoteCollateral::V4(collateral) = &mut collateral {
+            collateral.tcb_info_v4 = r#"{"tcbInfo":{"version":4,"tcbLevels":[]}}"#.to_string();
+        }
+        collateral.verify_signatures_with_root(&test_root(), NOW_SECS);
+    }
+
+    const QE_MRSIGNER: [u8; 32] = [0xab; 32];
+    const QE_ISVPRODID: u16 = 2;
+
+    fn qe_collateral() -> QuoteCollateralV3 {
+        let mut collateral = collateral_issued("2025-01-01T00:00:00Z", "2025-01-01T00:00:00Z");
+        collateral.qe_identity = format!(
+            r#"{{"enclaveIdentity":{{"mrsigner":"{}","isvprodid":{}}}}}"#,
+            encode(QE_MRSIGNER),
+            QE_ISVPRODID
+        );
+        collateral
+    }
+
+    // TDX v4 quote over `td_report`, attested by key 0x3333 and certified by the test leaf as PCK
+    fn build_quote(td_report: &[u8; TD_REPORT_LEN], pck_chain: &str) -> Vec<u8> {
+        let mut header = vec![0u8; QUOTE_HEADER_LEN];
+        header[0..2].copy_from_slice(&QUOTE_VERSION.to_le_bytes());
+        header[2..4].copy_from_slice(&ECDSA_P256_KEY_TYPE.to_le_bytes());
+        header[4..8].copy_from_slice(&TDX_TEE_TYPE.to_le_bytes());
+        let signed = [header.as_slice(), td_report].concat();
+
+        let attestation_key = public_key(0x3333);
+        let qe_auth_data = b"auth";
+        let mut qe_report = vec![0u8; QE_REPORT_LEN];
+        qe_report[QE_REPORT_MRSIGNER..QE_REPORT_MRSIGNER + 32].copy_from_slice(&QE_MRSIGNER);
+        qe_report[QE_REPORT_ISVPRODID..QE_REPORT_ISVPRODID + 2].copy_from_slice(&QE_ISVPRODID.to_le_bytes());
+        let key_hash = Sha256::new().chain_update(&attestation_key).chain_update(qe_auth_data).finalize();
+        qe_report[QE_REPORT_DATA..QE_REPORT_DATA + 32].copy_from_slice(&key_hash);
+
+        let qe_data = [
+            qe_report.as_slice(),
+            &sign(0x2222, &qe_report),
+            &(qe_auth_data.len() as u16).to_le_bytes(),
+            qe_auth_data,
+            &PCK_CERT_CHAIN_DATA.to_le_bytes(),
+            &(pck_chain.len() as u32).to_le_bytes(),
+            pck_chain.as_bytes(),
+        ]
+        .concat();
+        let signature_data = [
+            sign(0x3333, &signed).as_slice(),
+            &attestation_key,
+            &QE_REPORT_CERT_DATA.to_le_bytes(),
+            &(qe_data.len() as u32).to_le_bytes(),
+            &qe_data,
+        ]
+        .concat();
+        [signed.as_slice(), &(signature_data.len() as u32).to_le_bytes(), &signature_data].concat()
+    }
+
+    fn td_report() -> [u8; TD_REPORT_LEN] {
+        // RTMRn filled with n + 1, report data with 5
+        let mut td_report = [0u8; TD_REPORT_LEN];
+        for (index, byte) in td_report[TD_REPORT_RTMR0..TD_REPORT_DATA].iter_mut().enumerate() {
+            *byte = (index / 48) as u8 + 1;
+        }
+        td_report[TD_REPORT_DATA..].fill(5);
+        td_report
+    }
+
+    fn test_chain() -> String {
+        [TEST_PCK, TEST_ROOT].concat()
+    }
+
+    #[test]
+    fn verify_quote_returns_the_signed_td_report() {
+        let quote = build_quote(&td_report(), &test_chain());
+        let verified = verify_quote_with_root(&quote, &qe_collateral(), &test_root(), NOW_SECS);
+        assert_eq!(verified.rtmrs[0], "01".repeat(48));
+        assert_eq!(verified.rtmrs[3], "04".repeat(48));
+        assert_eq!(verified.report_data, [5; 64]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Quote signature does not match its attestation key")]
+    fn verify_quote_rejects_a_tampered_rtmr() {
+        let mut quote = build_quote(&td_report(), &test_chain());
+        quote[QUOTE_HEADER_LEN + TD_REPORT_RTMR0 + 3 * 48] ^= 1;
+        verify_quote_with_root(&quote, &qe_collateral(), &test_root(), NOW_SECS);
+    }
+
+    #[test]
+    #[should_panic(expected = "PCK issuer chain does not end at the pinned root CA")]
+    fn verify_quote_rejects_a_pck_chain_outside_the_intel_root() {
+        let quote = build_quote(&td_report(), &test_chain());
+        verify_quote(&quote, &qe_collateral(), NOW_SECS);
+    }
+
+    #[test]
+    #[should_panic(expected = "QE report does not match qe_identity")]
+    fn verify_quote_rejects_an_unknown_quoting_enclave() {
+        let quote = build_quote(&td_report(), &test_chain());
+        let mut collateral = qe_collateral();
+        collateral.qe_identity = collateral.qe_identity.replace(&encode(QE_MRSIGNER), &"cd".repeat(32));
+        verify_quote_with_root(&quote, &collateral, &test_root(), NOW_SECS);
+    }
+
+    #[test]
+    #[should_panic(expected = "Quote is truncated")]
+    fn verify_quote_rejects_a_truncated_quote() {
+        let quote = build_quote(&td_report(), &test_chain());
+        verify_quote_with_root(&quote[..quote.len() - 1], &qe_collateral(), &test_root(), NOW_SECS);
+    }
+
+    #[test]
+    fn verify_quote_reads_the_platform_tcb() {
+        let mut td_report = td_report();
+        td_report[TD_REPORT_TEE_TCB_SVN..TD_REPORT_TEE_TCB_SVN + 16].fill(7);
+        let quote = build_quote(&td_report, &test_chain());
+        let tcb = verify_quote_with_root(&quote, &qe_collateral(), &test_root(), NOW_SECS).tcb;
+        assert_eq!(tcb.fmspc, "00806f050000");
+        assert_eq!((tcb.sgx_svns, tcb.pcesvn, tcb.tdx_svns), ([3; 16], 11, [7; 16]));
+    }
+
+    // The test PCK's platform with every TDX component SVN at 2
+    fn platform() -> PlatformTcb {
+        PlatformTcb { fmspc: "00806f050000".to_string(), sgx_svns: [3; 16], pcesvn: 11, tdx_svns: [2; 16] }
+    }
+
+    fn tcb_level(sgx_svn: u8, tdx_svn: u8, status: &str) -> Value {
+        let components = |svn: u8| vec![serde_json::json!({ "svn": svn }); 16];
+        serde_json::json!({
+            "tcb": { "sgxtcbcomponents": components(sgx_svn), "pcesvn": 11, "tdxtcbcomponents": components(tdx_svn) },
+            "tcbStatus": status,
+        })
+    }
+
+    fn tcb_info(levels: Vec<Value>) -> String {
+        serde_json::json!({
+            "tcbInfo": {
+                "id": "TDX",
+                "fmspc": "00806F050000",
+                "nextUpdate": "2030-01-01T00:00:00Z",
+                "tcbStatus": "UpToDate",
+                "tcbLevels": levels,
+            }
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn tcb_status_is_the_first_level_the_platform_meets() {
+        let levels = vec![tcb_level(4, 2, "OutOfDate"), tcb_level(3, 2, "SWHardeningNeeded"), tcb_level(0, 0, "UpToDate")];
+        assert_eq!(check_tcb_info_status(&tcb_info(levels), &platform(), NOW_SECS), "SWHardeningNeeded");
+    }
+
+    #[test]
+    #[should_panic(expected = "TCB status OutOfDate is not allowed")]
+    fn tcb_status_rejects_an_out_of_date_platform() {
+        // Level 0 and the collection-wide status are UpToDate, but the platform's TDX SVNs only reach level 1
+        let levels = vec![tcb_level(3, 3, "UpToDate"), tcb_level(3, 2, "OutOfDate")];
+        check_tcb_info_status(&tcb_info(levels), &platform(), NOW_SECS);
+    }
+
+    #[test]
+    #[should_panic(expected = "Platform TCB is below every tcb_info level")]
+    fn tcb_status_rejects_a_platform_below_every_level() {
+        check_tcb_info_status(&tcb_info(vec![tcb_level(4, 0, "UpToDate")]), &platform(), NOW_SECS);
+    }
+
+    #[test]
+    #[should_panic(expected = "tcb_info is for a different FMSPC than the PCK certificate")]
+    fn tcb_status_rejects_another_fmspc() {
+        let mut platform = platform();
+        platform.fmspc = "00906ed50000".to_string();
+        check_tcb_info_status(&tcb_info(vec![tcb_level(0, 0, "UpToDate")]), &platform, NOW_SECS);
+    }
+
+    #[test]
+    fn parses_rfc3339_timestamps() {
+        assert_eq!(parse_rfc3339_secs("1970-01-01T00:00:00Z"), Some(0));
+        assert_eq!(parse_rfc3339_secs("2024-02-29T12:34:56Z"), Some(1_709_210_096));
+        assert_eq!(parse_rfc3339_secs("2025-06-01T00:00:00.123Z"), Some(1_748_736_000));
+    }
+
+    #[test]
+    fn rejects_out_of_range_timestamp_fields() {
+        for timestamp in [
+            "2025-13-01T00:00:00Z",
+            "2025-00-10T00:00:00Z",
+            "2025-01-00T00:00:00Z",
+            "2025-04-31T00:00:00Z",
+            "2025-02-29T00:00:00Z",
+            "2100-02-29T00:00:00Z",
+            "2025-01-01T24:00:00Z",
+            "2025-01-01T00:60:00Z",
+            "2025-01-01T00:00:61Z",
+            "2025-01-01T00:00:00",
+        ] {
+            assert_eq!(parse_rfc3339_secs(timestamp), None, "{}", timestamp);
+        }
+    }
+}
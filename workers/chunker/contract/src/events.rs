@@ -0,0 +1,65 @@
+// NEP-297 events for worker lifecycle changes, under the `vibesflow_worker` standard
+use near_sdk::serde::Serialize;
+use near_sdk::{env, AccountIdRef};
+
+const WORKER_STANDARD_NAME: &str = "vibesflow_worker";
+const WORKER_STANDARD_VERSION: &str = "1.0.0";
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct WorkerEvent<'a> {
+    pub account_id: &'a AccountIdRef,
+    pub codehash: Option<&'a str>, // None for dev-mode workers
+    pub timestamp: u64,
+}
+
+impl<'a> WorkerEvent<'a> {
+    pub fn new(account_id: &'a AccountIdRef, codehash: Option<&'a str>) -> Self {
+        Self {
+            account_id,
+            codehash,
+            timestamp: env::block_timestamp(),
+        }
+    }
+
+    // register_worker and register_worker_attested
+    pub fn emit_register(self) {
+        WorkerEventKind::WorkerRegister(&[self]).emit()
+    }
+
+    // deactivate_worker and admin_deactivate_worker
+    pub fn emit_deactivate(self) {
+        WorkerEventKind::WorkerDeactivate(&[self]).emit()
+    }
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+#[serde(tag = "event", content = "data")]
+#[serde(rename_all = "snake_case")]
+#[allow(clippy::enum_variant_names)]
+enum WorkerEventKind<'a> {
+    WorkerRegister(&'a [WorkerEvent<'a>]),
+    WorkerDeactivate(&'a [WorkerEvent<'a>]),
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+struct WorkerEventLog<'a> {
+    standard: &'static str,
+    version: &'static str,
+    #[serde(flatten)]
+    event_kind: WorkerEventKind<'a>,
+}
+
+impl WorkerEventKind<'_> {
+    fn emit(self) {
+        let event = WorkerEventLog {
+            standard: WORKER_STANDARD_NAME,
+            version: WORKER_STANDARD_VERSION,
+            event_kind: self,
+        };
+        let json = near_sdk::serde_json::to_string(&event).unwrap_or_else(|_| env::abort());
+        env::log_str(&format!("EVENT_JSON:{}", json));
+    }
+}
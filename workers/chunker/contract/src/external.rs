@@ -37,6 +37,7 @@ pub struct Scalar {
     pub scalar: String,
 }
 
+#[allow(dead_code)]
 #[ext_contract(ext_signer)]
 pub trait SignerInterface {
     fn sign(&mut self, request: SignRequest) -> PromiseOrValue<SignResult>;
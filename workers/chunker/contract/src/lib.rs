@@ -1,12 +1,33 @@
-use near_sdk::store::{IterableMap, IterableSet};
+use near_sdk::store::{IterableMap, IterableSet, LookupMap};
 use near_sdk::{
     env, near, require,
-    AccountId, PanicOnDefault, Promise,
+    AccountId, CurveType, Gas, NearToken, PanicOnDefault, Promise, PromiseError, PublicKey,
 };
+use external::SignResult;
 
 mod external;
 mod ecdsa;
+mod events;
+mod migrate;
+#[cfg(test)]
+mod tests;
 mod utils;
+mod vrf;
+
+// Most recent sign_callback outcomes kept per worker
+const MAX_SIGN_OUTCOMES_PER_WORKER: usize = 20;
+const SIGN_CALLBACK_GAS: Gas = Gas::from_tgas(10);
+
+// sign_tx calls each worker may make per window unless the owner changes it
+const DEFAULT_MAX_SIGNS_PER_WINDOW: u32 = 30;
+const DEFAULT_SIGN_WINDOW_NS: u64 = 60 * 1_000_000_000; // 1 minute
+
+// Minimum time between a worker's registration or key rotations. A worker that could rotate at
+// will could try fresh keys against a known payload and pick the VRF output it likes
+const KEY_ROTATION_COOLDOWN_NS: u64 = 24 * 60 * 60 * 1_000_000_000; // 1 day
+
+// Oldest collateral register_worker_attested accepts, matching the dispatcher's default
+const MAX_ATTESTATION_AGE_NS: u64 = 7 * 24 * 60 * 60 * 1_000_000_000; // 7 days
 
 // Worker registration structure
 #[near(serializers = [json, borsh])]
@@ -16,6 +37,32 @@ pub struct Worker {
     pub public_key: String,
     pub registered_at: u64,
     pub is_active: bool,
+    pub deactivated_by_owner: bool, // set by admin_deactivate_worker; only the owner can undo it
+    pub codehash: Option<String>, // None for dev-mode registrations
+    pub rotated_at: Option<u64>,  // last rotate_public_key, if any
+    pub last_seen: u64,           // registration or latest heartbeat
+    pub sign_window: (u64, u32),  // (window start ns, sign_tx calls in that window)
+}
+
+// On-chain record of a verified VRF submission
+#[near(serializers = [json, borsh])]
+#[derive(Clone)]
+pub struct VrfRecord {
+    pub worker: AccountId,
+    pub proof: String,
+    pub output: String,
+    pub submitted_at: u64,
+}
+
+// Result of one MPC sign call, as observed by sign_callback
+#[near(serializers = [json, borsh])]
+#[derive(Clone)]
+pub struct SignOutcome {
+    pub rta_id: String,
+    pub chunk_id: String,
+    pub success: bool,
+    pub signature_hash: Option<String>, // hex sha256 of the signature; None if the MPC call failed
+    pub timestamp: u64,
 }
 
 #[near(contract_state)]
@@ -25,6 +72,19 @@ pub struct Contract {
     pub worker_by_account_id: IterableMap<AccountId, Worker>,
     pub approved_codehashes: IterableSet<String>,
     pub mpc_contract: AccountId,
+    pub vrf_outputs: LookupMap<Vec<u8>, String>, // payload -> hex VRF output
+    pub vrf_proofs: IterableMap<String, VrfRecord>, // chunk or payload id -> record
+    pub dev_mode: bool, // allows unattested register_worker
+    pub mpc_gas: Gas,
+    pub mpc_deposit: NearToken,
+    pub sign_outcomes: LookupMap<AccountId, Vec<SignOutcome>>, // worker -> recent outcomes
+    pub pending_owner: Option<AccountId>, // proposed by propose_owner, awaiting accept_owner
+    pub paused: bool, // incident kill switch for signing, VRF submission and registration
+    pub approved_mpc_contracts: IterableSet<AccountId>, // allowed sign_tx mpc_override targets
+    pub worker_paths: IterableMap<AccountId, IterableSet<String>>, // worker -> derivation paths it may sign under
+    pub max_signs_per_window: u32,
+    pub sign_window_ns: u64,
+    pub expected_compose_hashes: IterableSet<String>, // approved app compose hashes; empty disables the check
 }
 
 #[near]
@@ -36,6 +96,19 @@ impl Contract {
             worker_by_account_id: IterableMap::new(b"w"),
             approved_codehashes: IterableSet::new(b"c"),
             mpc_contract: "v1.signer-prod.testnet".parse().unwrap(),
+            vrf_outputs: LookupMap::new(b"v"),
+            vrf_proofs: IterableMap::new(b"p"),
+            dev_mode: false,
+            mpc_gas: ecdsa::DEFAULT_MPC_GAS,
+            mpc_deposit: ecdsa::DEFAULT_MPC_DEPOSIT,
+            sign_outcomes: LookupMap::new(b"s"),
+            pending_owner: None,
+            paused: false,
+            approved_mpc_contracts: IterableSet::new(b"m"),
+            worker_paths: IterableMap::new(b"d"),
+            max_signs_per_window: DEFAULT_MAX_SIGNS_PER_WINDOW,
+            sign_window_ns: DEFAULT_SIGN_WINDOW_NS,
+            expected_compose_hashes: IterableSet::new(b"h"),
         }
     }
 
@@ -50,29 +123,156 @@ impl Contract {
         self.approved_codehashes.remove(&codehash);
     }
 
+    // Batch variants for deploys that ship several worker images; both return how many changed
+    pub fn approve_codehashes(&mut self, codehashes: Vec<String>) -> u32 {
+        self.require_owner();
+        codehashes
+            .into_iter()
+            .filter(|codehash| self.approved_codehashes.insert(codehash.clone()))
+            .count() as u32
+    }
+
+    pub fn remove_codehashes(&mut self, codehashes: Vec<String>) -> u32 {
+        self.require_owner();
+        codehashes
+            .iter()
+            .filter(|codehash| self.approved_codehashes.remove(*codehash))
+            .count() as u32
+    }
+
+    // Pins the whole app compose (env, mounts, secrets) that attested workers may run, on top
+    // of its image codehash
+    pub fn approve_compose_hash(&mut self, compose_hash: String) {
+        self.require_owner();
+        self.expected_compose_hashes.insert(compose_hash);
+    }
+
+    pub fn remove_compose_hash(&mut self, compose_hash: String) {
+        self.require_owner();
+        self.expected_compose_hashes.remove(&compose_hash);
+    }
+
+    pub fn get_expected_compose_hashes(&self) -> Vec<String> {
+        self.expected_compose_hashes.iter().cloned().collect()
+    }
+
+    // Per-worker sign_tx budget: at most max_signs_per_window calls every sign_window_ns
+    pub fn set_sign_rate_limit(&mut self, max_signs_per_window: u32, sign_window_ns: u64) {
+        self.require_owner();
+        self.max_signs_per_window = max_signs_per_window;
+        self.sign_window_ns = sign_window_ns;
+    }
+
+    pub fn get_sign_rate_limit(&self) -> (u32, u64) {
+        (self.max_signs_per_window, self.sign_window_ns)
+    }
+
+    // Owner-approved derivation paths per worker; sign_tx only signs under the caller's own
+    // paths, so one worker can't request signatures with another's MPC-derived key
+    pub fn register_path(&mut self, account_id: AccountId, path: String) {
+        self.require_owner();
+        let prefix = [b"e".as_slice(), &env::sha256(account_id.as_bytes())].concat();
+        self.worker_paths
+            .entry(account_id)
+            .or_insert_with(|| IterableSet::new(prefix))
+            .insert(path);
+    }
+
+    pub fn remove_path(&mut self, account_id: AccountId, path: String) {
+        self.require_owner();
+        let Some(paths) = self.worker_paths.get_mut(&account_id) else {
+            return;
+        };
+        paths.remove(&path);
+        if paths.is_empty() {
+            self.worker_paths.remove(&account_id);
+        }
+    }
+
+    pub fn get_worker_paths(&self, account_id: AccountId) -> Vec<String> {
+        self.worker_paths
+            .get(&account_id)
+            .map(|paths| paths.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    // Two-step ownership transfer: the owner proposes, the proposed account accepts
+    pub fn propose_owner(&mut self, new_owner: AccountId) {
+        self.require_owner();
+        self.pending_owner = Some(new_owner);
+    }
+
+    pub fn accept_owner(&mut self) {
+        let predecessor = env::predecessor_account_id();
+        require!(
+            self.pending_owner.as_ref() == Some(&predecessor),
+            "Only the pending owner can accept ownership"
+        );
+        self.owner = predecessor;
+        self.pending_owner = None;
+    }
+
+    pub fn get_pending_owner(&self) -> Option<AccountId> {
+        self.pending_owner.clone()
+    }
+
     pub fn set_mpc_contract(&mut self, mpc_contract: AccountId) {
         self.require_owner();
         self.mpc_contract = mpc_contract;
     }
 
+    // Extra MPC signers sign_tx may be pointed at, e.g. during a blue/green signer migration
+    pub fn approve_mpc_contract(&mut self, mpc_contract: AccountId) {
+        self.require_owner();
+        self.approved_mpc_contracts.insert(mpc_contract);
+    }
+
+    pub fn remove_mpc_contract(&mut self, mpc_contract: AccountId) {
+        self.require_owner();
+        self.approved_mpc_contracts.remove(&mpc_contract);
+    }
+
+    // Gas and deposit attached to the MPC sign call made by sign_tx
+    pub fn set_mpc_gas(&mut self, mpc_gas: Gas) {
+        self.require_owner();
+        self.mpc_gas = mpc_gas;
+    }
+
+    pub fn set_mpc_deposit(&mut self, mpc_deposit: NearToken) {
+        self.require_owner();
+        self.mpc_deposit = mpc_deposit;
+    }
+
+    pub fn set_dev_mode(&mut self, dev_mode: bool) {
+        self.require_owner();
+        self.dev_mode = dev_mode;
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.require_owner();
+        self.paused = paused;
+    }
+
     // Worker registration functions
+    // Development registration, only while dev_mode is on
     pub fn register_worker(&mut self, public_key: String) {
-        let account_id = env::predecessor_account_id();
-        
-        // Check if already registered
-        require!(
-            !self.worker_by_account_id.contains_key(&account_id),
-            "Worker already registered"
-        );
-
-        let worker = Worker {
-            account_id: account_id.clone(),
-            public_key,
-            registered_at: env::block_timestamp(),
-            is_active: true,
-        };
+        require!(self.dev_mode, "Dev registration is disabled");
+        self.insert_worker(public_key, None);
+    }
 
-        self.worker_by_account_id.insert(account_id, worker);
+    // Production registration under an approved codehash. `tee_proof` is a JSON
+    // attestation::TeeVerificationProof whose quote is bound to the caller's account
+    pub fn register_worker_attested(&mut self, public_key: String, codehash: String, tee_proof: String) {
+        require!(self.is_codehash_approved(codehash.clone()), "Codehash not approved");
+        let compose_hashes = &self.expected_compose_hashes;
+        let attestation = attestation::verify_tee_proof(
+            &tee_proof,
+            env::predecessor_account_id().as_str(),
+            MAX_ATTESTATION_AGE_NS,
+            |compose_hash| compose_hashes.is_empty() || compose_hashes.contains(compose_hash),
+        );
+        require!(attestation.codehash == codehash, "Codehash does not match attestation");
+        self.insert_worker(public_key, Some(codehash));
     }
 
     pub fn deactivate_worker(&mut self) {
@@ -81,19 +281,117 @@ impl Contract {
         if let Some(worker) = self.worker_by_account_id.get(&account_id) {
             let mut updated_worker = worker.clone();
             updated_worker.is_active = false;
+            events::WorkerEvent::new(&account_id, updated_worker.codehash.as_deref()).emit_deactivate();
             self.worker_by_account_id.insert(account_id, updated_worker);
         } else {
             panic!("Worker not found");
         }
     }
 
+    // Owner kill switch for a worker that won't deactivate itself (e.g. suspected TEE compromise)
+    pub fn admin_deactivate_worker(&mut self, account_id: AccountId) {
+        self.require_owner();
+        let mut worker = self
+            .worker_by_account_id
+            .get(&account_id)
+            .cloned()
+            .unwrap_or_else(|| env::panic_str("Worker not found"));
+        worker.is_active = false;
+        worker.deactivated_by_owner = true;
+        env::log_str(&format!("Worker {} deactivated by owner", account_id));
+        events::WorkerEvent::new(&account_id, worker.codehash.as_deref()).emit_deactivate();
+        self.worker_by_account_id.insert(account_id, worker);
+    }
+
+    // Inverse of deactivate_worker; keeps the original registration record
+    pub fn reactivate_worker(&mut self) {
+        let account_id = env::predecessor_account_id();
+        let mut worker = self
+            .worker_by_account_id
+            .get(&account_id)
+            .cloned()
+            .unwrap_or_else(|| env::panic_str("Worker not found"));
+        require!(!worker.deactivated_by_owner, "Worker was deactivated by the owner");
+        worker.is_active = true;
+        self.worker_by_account_id.insert(account_id, worker);
+    }
+
+    // Inverse of admin_deactivate_worker
+    pub fn admin_reactivate_worker(&mut self, account_id: AccountId) {
+        self.require_owner();
+        let mut worker = self
+            .worker_by_account_id
+            .get(&account_id)
+            .cloned()
+            .unwrap_or_else(|| env::panic_str("Worker not found"));
+        worker.is_active = true;
+        worker.deactivated_by_owner = false;
+        env::log_str(&format!("Worker {} reactivated by owner", account_id));
+        self.worker_by_account_id.insert(account_id, worker);
+    }
+
+    // Swap the worker's signing key in place so its account and registered_at stay stable
+    pub fn rotate_public_key(&mut self, new_public_key: String) {
+        self.require_not_paused();
+        self.require_registered_worker();
+        let account_id = env::predecessor_account_id();
+        let mut worker = self.worker_by_account_id.get(&account_id).cloned().unwrap();
+        let now = env::block_timestamp();
+        require!(
+            now.saturating_sub(worker.rotated_at.unwrap_or(worker.registered_at)) >= KEY_ROTATION_COOLDOWN_NS,
+            "Key rotated too recently"
+        );
+        worker.public_key = new_public_key;
+        worker.rotated_at = Some(now);
+        self.worker_by_account_id.insert(account_id, worker);
+    }
+
+    // Liveness ping; workers call this periodically so get_stale_workers can spot dead ones
+    pub fn heartbeat(&mut self) {
+        self.require_registered_worker();
+        if let Some(worker) = self.worker_by_account_id.get_mut(&env::predecessor_account_id()) {
+            worker.last_seen = env::block_timestamp();
+        }
+    }
+
     // VRF Proof submission
-    pub fn submit_vrf_proof(&mut self, _payload: Vec<u8>, proof: String) {
+    // `proof` is a hex ECVRF-EDWARDS25519-SHA512-TAI proof over `payload` by the worker's ed25519 key;
+    // the verified submission is recorded under `key` (a chunk or payload identifier)
+    pub fn submit_vrf_proof(&mut self, key: String, payload: Vec<u8>, proof: String) {
+        self.require_not_paused();
         self.require_registered_worker();
-        
-        // Store the VRF proof
-        // We are currently in 'dev mode' - in prod this is where we will verify the proof
-        env::log_str(&format!("VRF proof submitted: {}", proof));
+        require!(!self.vrf_proofs.contains_key(&key), "VRF proof already recorded for key");
+        require!(
+            !self.vrf_outputs.contains_key(&payload),
+            "VRF output already recorded for payload"
+        );
+
+        let account_id = env::predecessor_account_id();
+        let worker = self.worker_by_account_id.get(&account_id).unwrap();
+        let public_key: PublicKey = worker
+            .public_key
+            .parse()
+            .unwrap_or_else(|_| env::panic_str("Worker public key is invalid"));
+        require!(
+            public_key.curve_type() == CurveType::ED25519,
+            "Worker public key must be ed25519"
+        );
+        let proof_bytes = hex::decode(&proof).unwrap_or_else(|_| env::panic_str("VRF proof is not hex"));
+        let output = vrf::verify(&utils::vec_to_fixed(public_key.as_bytes()[1..].to_vec()), &payload, &proof_bytes)
+            .unwrap_or_else(|| env::panic_str("Invalid VRF proof"));
+
+        let output = hex::encode(output);
+        env::log_str(&format!("VRF output: {}", output));
+        self.vrf_outputs.insert(payload, output.clone());
+        self.vrf_proofs.insert(
+            key,
+            VrfRecord {
+                worker: account_id,
+                proof,
+                output,
+                submitted_at: env::block_timestamp(),
+            },
+        );
     }
 
     // MPC signature function
@@ -102,11 +400,70 @@ impl Contract {
         payload: Vec<u8>,
         derivation_path: String,
         key_version: u32,
+        rta_id: String,
+        chunk_id: String,
+        mpc_override: Option<AccountId>,
     ) -> Promise {
+        self.require_not_paused();
         self.require_registered_worker();
+        self.consume_sign_quota();
+        require!(
+            self.worker_paths
+                .get(&env::predecessor_account_id())
+                .is_some_and(|paths| paths.contains(&derivation_path)),
+            "Derivation path not registered for worker"
+        );
+        if let Some(mpc_contract) = &mpc_override {
+            require!(
+                self.approved_mpc_contracts.contains(mpc_contract),
+                "MPC contract not approved"
+            );
+        }
 
-        // Call the MPC contract to get a signature for the payload
-        ecdsa::get_sig(payload, derivation_path, key_version)
+        // Call the MPC contract to get a signature for the payload, then record how it went
+        let signature = ecdsa::get_sig(
+            payload,
+            derivation_path,
+            key_version,
+            mpc_override,
+            self.mpc_gas,
+            self.mpc_deposit,
+        );
+        signature.then(
+            Self::ext(env::current_account_id())
+                .with_static_gas(SIGN_CALLBACK_GAS)
+                .sign_callback(env::predecessor_account_id(), rta_id, chunk_id),
+        )
+    }
+
+    // Records the MPC outcome against the worker and passes the signature through to the caller
+    #[private]
+    pub fn sign_callback(
+        &mut self,
+        worker_id: AccountId,
+        rta_id: String,
+        chunk_id: String,
+        #[callback_result] result: Result<SignResult, PromiseError>,
+    ) -> Option<SignResult> {
+        let signature = result.ok();
+        let signature_hash = signature.as_ref().map(ecdsa::signature_hash);
+        match &signature_hash {
+            Some(hash) => env::log_str(&format!("MPC signature for {}/{}: {}", rta_id, chunk_id, hash)),
+            None => env::log_str(&format!("MPC signing failed for {}/{}", rta_id, chunk_id)),
+        }
+
+        let outcomes = self.sign_outcomes.entry(worker_id).or_default();
+        if outcomes.len() >= MAX_SIGN_OUTCOMES_PER_WORKER {
+            outcomes.remove(0);
+        }
+        outcomes.push(SignOutcome {
+            rta_id,
+            chunk_id,
+            success: signature.is_some(),
+            signature_hash,
+            timestamp: env::block_timestamp(),
+        });
+        signature
     }
 
     // View functions
@@ -126,11 +483,95 @@ impl Contract {
         self.worker_by_account_id.get(&account_id).cloned()
     }
 
+    // Active workers that have not registered or sent a heartbeat within the last `max_idle_ns`
+    pub fn get_stale_workers(&self, max_idle_ns: u64) -> Vec<AccountId> {
+        let now = env::block_timestamp();
+        self.worker_by_account_id
+            .iter()
+            .filter(|(_, worker)| worker.is_active && now.saturating_sub(worker.last_seen) > max_idle_ns)
+            .map(|(account_id, _)| account_id.clone())
+            .collect()
+    }
+
+    pub fn get_vrf_output(&self, payload: Vec<u8>) -> Option<String> {
+        self.vrf_outputs.get(&payload).cloned()
+    }
+
+    pub fn get_vrf_proof(&self, key: String) -> Option<VrfRecord> {
+        self.vrf_proofs.get(&key).cloned()
+    }
+
+    pub fn is_dev_mode(&self) -> bool {
+        self.dev_mode
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    // Lets a worker confirm an MPC SignResult recovers to its derived public key (hex,
+    // compressed or uncompressed) before submitting the signed transaction
+    pub fn verify_signature(&self, payload: Vec<u8>, signature: SignResult, expected_pubkey: String) -> bool {
+        let (Some(signature), Ok(expected_pubkey)) =
+            (ecdsa::signature_bytes(&signature), hex::decode(expected_pubkey))
+        else {
+            return false;
+        };
+        ecdsa::verify_sig(&payload, &signature, &expected_pubkey)
+    }
+
+    // Last MPC signing outcomes for a worker, oldest first
+    pub fn get_sign_outcomes(&self, account_id: AccountId) -> Vec<SignOutcome> {
+        self.sign_outcomes.get(&account_id).cloned().unwrap_or_default()
+    }
+
     pub fn get_mpc_contract(&self) -> AccountId {
         self.mpc_contract.clone()
     }
 
+    pub fn get_approved_mpc_contracts(&self) -> Vec<AccountId> {
+        self.approved_mpc_contracts.iter().cloned().collect()
+    }
+
+    pub fn get_mpc_gas(&self) -> Gas {
+        self.mpc_gas
+    }
+
+    pub fn get_mpc_deposit(&self) -> NearToken {
+        self.mpc_deposit
+    }
+
     // Private helper functions
+    fn insert_worker(&mut self, public_key: String, codehash: Option<String>) {
+        self.require_not_paused();
+        let account_id = env::predecessor_account_id();
+
+        // Check if already registered
+        require!(
+            !self.worker_by_account_id.contains_key(&account_id),
+            "Worker already registered"
+        );
+
+        let worker = Worker {
+            account_id: account_id.clone(),
+            public_key,
+            registered_at: env::block_timestamp(),
+            is_active: true,
+            deactivated_by_owner: false,
+            codehash,
+            rotated_at: None,
+            last_seen: env::block_timestamp(),
+            sign_window: (0, 0),
+        };
+
+        events::WorkerEvent::new(&account_id, worker.codehash.as_deref()).emit_register();
+        self.worker_by_account_id.insert(account_id, worker);
+    }
+
+    fn require_not_paused(&self) {
+        require!(!self.paused, "Contract paused");
+    }
+
     fn require_owner(&self) {
         require!(
             env::predecessor_account_id() == self.owner,
@@ -138,11 +579,38 @@ impl Contract {
         );
     }
 
+    // Counts a sign_tx call against the caller's window, starting a new window once the
+    // current one has elapsed
+    fn consume_sign_quota(&mut self) {
+        let now = env::block_timestamp();
+        let (max_signs, window_ns) = (self.max_signs_per_window, self.sign_window_ns);
+        let worker = self
+            .worker_by_account_id
+            .get_mut(&env::predecessor_account_id())
+            .unwrap_or_else(|| env::panic_str("Worker not registered"));
+        let (window_start, count) = &mut worker.sign_window;
+        if now.saturating_sub(*window_start) >= window_ns {
+            *window_start = now;
+            *count = 0;
+        }
+        require!(*count < max_signs, "Rate limit exceeded");
+        *count += 1;
+    }
+
     fn require_registered_worker(&self) {
         let predecessor = env::predecessor_account_id();
-        require!(
-            self.worker_by_account_id.contains_key(&predecessor),
-            "Worker not registered"
-        );
+        let worker = self
+            .worker_by_account_id
+            .get(&predecessor)
+            .unwrap_or_else(|| env::panic_str("Worker not registered"));
+        require!(worker.is_active, "Worker is deactivated");
+        // Removing a codehash (or leaving dev mode) invalidates the workers registered under it
+        match &worker.codehash {
+            Some(codehash) => require!(
+                self.approved_codehashes.contains(codehash),
+                "Worker codehash no longer approved"
+            ),
+            None => require!(self.dev_mode, "Dev registration is disabled"),
+        }
     }
 } 
\ No newline at end of file
@@ -0,0 +1,100 @@
+// State migration from the layout the chunker was first deployed with
+use crate::*;
+
+#[near(serializers = [borsh])]
+struct OldWorker {
+    account_id: AccountId,
+    public_key: String,
+    registered_at: u64,
+    is_active: bool,
+}
+
+#[near(serializers = [borsh])]
+struct OldContract {
+    owner: AccountId,
+    worker_by_account_id: IterableMap<AccountId, OldWorker>,
+    approved_codehashes: IterableSet<String>,
+    mpc_contract: AccountId,
+}
+
+#[near]
+impl Contract {
+    // Rewrites every worker into the current layout in one call, so the worker count must
+    // fit in its gas. Workers count as seen at migration so they aren't reported stale at once
+    #[private]
+    #[init(ignore_state)]
+    pub fn migrate() -> Self {
+        let mut old: OldContract =
+            env::state_read().unwrap_or_else(|| env::panic_str("Contract state not found"));
+        let old_workers: Vec<_> = old.worker_by_account_id.drain().collect();
+        // Flush the removals before the new map rewrites the same prefix
+        drop(old.worker_by_account_id);
+
+        let mut contract = Self::init(old.owner);
+        contract.approved_codehashes = old.approved_codehashes;
+        contract.mpc_contract = old.mpc_contract;
+        let now = env::block_timestamp();
+        for (account_id, worker) in old_workers {
+            let worker = Worker {
+                account_id: worker.account_id,
+                public_key: worker.public_key,
+                registered_at: worker.registered_at,
+                is_active: worker.is_active,
+                deactivated_by_owner: false,
+                codehash: None,
+                rotated_at: None,
+                last_seen: now,
+                sign_window: (0, 0),
+            };
+            contract.worker_by_account_id.insert(account_id, worker);
+        }
+        contract
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::testing_env;
+
+    #[test]
+    fn migrate_keeps_workers_and_settings() {
+        testing_env!(VMContextBuilder::new().block_timestamp(42).build());
+        let mut old = OldContract {
+            owner: accounts(0),
+            worker_by_account_id: IterableMap::new(b"w"),
+            approved_codehashes: IterableSet::new(b"c"),
+            mpc_contract: "signer.near".parse().unwrap(),
+        };
+        old.approved_codehashes.insert("c0dehash".to_string());
+        let worker = OldWorker {
+            account_id: accounts(1),
+            public_key: "ed25519:key".to_string(),
+            registered_at: 7,
+            is_active: false,
+        };
+        old.worker_by_account_id.insert(accounts(1), worker);
+        old.approved_codehashes.flush();
+        old.worker_by_account_id.flush();
+        env::state_write(&old);
+
+        let contract = Contract::migrate();
+
+        assert_eq!(contract.get_owner(), accounts(0));
+        assert!(contract.is_codehash_approved("c0dehash".to_string()));
+        assert_eq!(contract.get_mpc_contract(), "signer.near".parse::<AccountId>().unwrap());
+        let worker = contract.get_worker(accounts(1)).unwrap();
+        assert_eq!(worker.account_id, accounts(1));
+        assert_eq!(worker.public_key, "ed25519:key");
+        assert_eq!((worker.registered_at, worker.is_active), (7, false));
+        assert_eq!((worker.codehash, worker.rotated_at, worker.last_seen), (None, None, 42));
+    }
+
+    #[test]
+    #[should_panic(expected = "Contract state not found")]
+    fn migrate_without_state_fails() {
+        testing_env!(VMContextBuilder::new().build());
+        Contract::migrate();
+    }
+}
@@ -0,0 +1,215 @@
+use super::*;
+use near_sdk::test_utils::{accounts, VMContextBuilder};
+use near_sdk::testing_env;
+
+const PUBLIC_KEY: &str = "ed25519:FVen3X669xLzsi6N2V91DoiyzHzg1uAgqiT8jZ9nS96Z";
+
+fn set_caller(predecessor: AccountId) {
+    testing_env!(VMContextBuilder::new()
+        .current_account_id("chunker.near".parse().unwrap())
+        .predecessor_account_id(predecessor.clone())
+        .signer_account_id(predecessor)
+        .build());
+}
+
+/// Contract owned by `accounts(0)` with dev registration enabled.
+fn setup() -> Contract {
+    set_caller(accounts(0));
+    let mut contract = Contract::init(accounts(0));
+    contract.set_dev_mode(true);
+    contract
+}
+
+/// Dev-registers `worker` under `PUBLIC_KEY`.
+fn register(contract: &mut Contract, worker: AccountId) {
+    set_caller(worker);
+    contract.register_worker(PUBLIC_KEY.to_string());
+}
+
+#[test]
+fn worker_can_reactivate_itself() {
+    let mut contract = setup();
+    register(&mut contract, accounts(1));
+    contract.deactivate_worker();
+    contract.reactivate_worker();
+    assert!(contract.get_worker(accounts(1)).unwrap().is_active);
+}
+
+#[test]
+#[should_panic(expected = "Worker was deactivated by the owner")]
+fn owner_deactivated_worker_cannot_reactivate_itself() {
+    let mut contract = setup();
+    register(&mut contract, accounts(1));
+    set_caller(accounts(0));
+    contract.admin_deactivate_worker(accounts(1));
+
+    set_caller(accounts(1));
+    contract.reactivate_worker();
+}
+
+#[test]
+fn owner_can_reactivate_a_worker_it_deactivated() {
+    let mut contract = setup();
+    register(&mut contract, accounts(1));
+    set_caller(accounts(0));
+    contract.admin_deactivate_worker(accounts(1));
+    contract.admin_reactivate_worker(accounts(1));
+
+    let worker = contract.get_worker(accounts(1)).unwrap();
+    assert!(worker.is_active && !worker.deactivated_by_owner);
+}
+
+#[test]
+#[should_panic(expected = "Worker is deactivated")]
+fn deactivated_worker_cannot_sign() {
+    let mut contract = setup();
+    register(&mut contract, accounts(1));
+    contract.deactivate_worker();
+
+    let _ = contract.sign_tx(vec![0; 32], "path".to_string(), 0, "rta".to_string(), "c0".to_string(), None);
+}
+
+#[test]
+#[should_panic(expected = "Worker is deactivated")]
+fn deactivated_worker_cannot_submit_vrf_proofs() {
+    let mut contract = setup();
+    register(&mut contract, accounts(1));
+    contract.deactivate_worker();
+
+    contract.submit_vrf_proof("c0".to_string(), vec![], "00".repeat(80));
+}
+
+#[test]
+fn dev_registration_has_no_codehash() {
+    let mut contract = setup();
+    register(&mut contract, accounts(1));
+    assert_eq!(contract.get_worker(accounts(1)).unwrap().codehash, None);
+
+    // Dev workers stay usable for as long as dev mode is on
+    contract.heartbeat();
+}
+
+#[test]
+#[should_panic(expected = "Dev registration is disabled")]
+fn dev_worker_is_rejected_once_dev_mode_is_off() {
+    let mut contract = setup();
+    register(&mut contract, accounts(1));
+    set_caller(accounts(0));
+    contract.set_dev_mode(false);
+
+    set_caller(accounts(1));
+    let _ = contract.sign_tx(vec![0; 32], "path".to_string(), 0, "rta".to_string(), "c0".to_string(), None);
+}
+
+#[test]
+#[should_panic(expected = "Worker codehash no longer approved")]
+fn sign_tx_fails_after_the_codehash_is_removed() {
+    let mut contract = setup();
+    contract.approve_codehash("c0dehash".to_string());
+    // Stands in for register_worker_attested, which needs a real TEE proof
+    let worker = Worker {
+        account_id: accounts(1),
+        public_key: PUBLIC_KEY.to_string(),
+        registered_at: 0,
+        is_active: true,
+        deactivated_by_owner: false,
+        codehash: Some("c0dehash".to_string()),
+        rotated_at: None,
+        last_seen: 0,
+        sign_window: (0, 0),
+    };
+    contract.worker_by_account_id.insert(accounts(1), worker);
+    contract.remove_codehash("c0dehash".to_string());
+
+    set_caller(accounts(1));
+    let _ = contract.sign_tx(vec![0; 32], "path".to_string(), 0, "rta".to_string(), "c0".to_string(), None);
+}
+
+// RFC 9381 ECVRF-EDWARDS25519-SHA512-TAI proof over an empty payload by PUBLIC_KEY
+const VRF_PROOF: &str = "8657106690b5526245a92b003bb079ccd1a92130477671f6fc01ad16f26f723f26f8a57ccaed74ee1b190bed1f479d9727d2d0f9b005a6e456a35d4fb0daab1268a1b0db10836d9826a528ca76567805";
+
+#[test]
+fn submit_vrf_proof_records_the_output() {
+    let mut contract = setup();
+    register(&mut contract, accounts(1));
+    contract.submit_vrf_proof("c0".to_string(), vec![], VRF_PROOF.to_string());
+
+    let output = contract.get_vrf_output(vec![]).unwrap();
+    assert!(output.starts_with("90cf1df3b703cce5"));
+    assert_eq!(contract.get_vrf_proof("c0".to_string()).unwrap().output, output);
+}
+
+#[test]
+#[should_panic(expected = "Invalid VRF proof")]
+fn submit_vrf_proof_rejects_a_proof_for_another_payload() {
+    let mut contract = setup();
+    register(&mut contract, accounts(1));
+    contract.submit_vrf_proof("c0".to_string(), vec![0x72], VRF_PROOF.to_string());
+}
+
+#[test]
+#[should_panic(expected = "Invalid TEE verification proof")]
+fn attested_registration_verifies_the_tee_proof() {
+    let mut contract = setup();
+    contract.approve_codehash("c0dehash".to_string());
+
+    set_caller(accounts(1));
+    contract.register_worker_attested(PUBLIC_KEY.to_string(), "c0dehash".to_string(), "attested".to_string());
+}
+
+fn set_caller_at(predecessor: AccountId, block_timestamp: u64) {
+    testing_env!(VMContextBuilder::new()
+        .current_account_id("chunker.near".parse().unwrap())
+        .predecessor_account_id(predecessor)
+        .block_timestamp(block_timestamp)
+        .build());
+}
+
+#[test]
+fn rotate_public_key_keeps_the_registration() {
+    let mut contract = setup();
+    set_caller_at(accounts(1), 10);
+    contract.register_worker(PUBLIC_KEY.to_string());
+
+    let rotated_at = 10 + KEY_ROTATION_COOLDOWN_NS;
+    set_caller_at(accounts(1), rotated_at);
+    contract.rotate_public_key("ed25519:rotated".to_string());
+    let worker = contract.get_worker(accounts(1)).unwrap();
+    assert_eq!(worker.public_key, "ed25519:rotated");
+    assert_eq!((worker.registered_at, worker.rotated_at), (10, Some(rotated_at)));
+}
+
+#[test]
+#[should_panic(expected = "Key rotated too recently")]
+fn rotate_public_key_enforces_the_cooldown() {
+    let mut contract = setup();
+    register(&mut contract, accounts(1));
+    set_caller_at(accounts(1), KEY_ROTATION_COOLDOWN_NS);
+    contract.rotate_public_key("ed25519:first".to_string());
+    set_caller_at(accounts(1), 2 * KEY_ROTATION_COOLDOWN_NS - 1);
+    contract.rotate_public_key("ed25519:second".to_string());
+}
+
+#[test]
+#[should_panic(expected = "Worker is deactivated")]
+fn owner_deactivated_worker_cannot_rotate_its_key() {
+    let mut contract = setup();
+    register(&mut contract, accounts(1));
+    set_caller(accounts(0));
+    contract.admin_deactivate_worker(accounts(1));
+
+    set_caller_at(accounts(1), KEY_ROTATION_COOLDOWN_NS);
+    contract.rotate_public_key("ed25519:rotated".to_string());
+}
+
+#[test]
+#[should_panic(expected = "Contract paused")]
+fn rotate_public_key_is_blocked_while_paused() {
+    let mut contract = setup();
+    register(&mut contract, accounts(1));
+    set_caller(accounts(0));
+    contract.set_paused(true);
+
+    set_caller_at(accounts(1), KEY_ROTATION_COOLDOWN_NS);
+    contract.rotate_public_key("ed25519:rotated".to_string());
+}
@@ -0,0 +1,107 @@
+// ECVRF-EDWARDS25519-SHA512-TAI verification (RFC 9381, section 5.3)
+use curve25519_dalek::edwards::{CompressedEdwardsY, EdwardsPoint};
+use curve25519_dalek::scalar::Scalar;
+use sha2::{Digest, Sha512};
+
+const SUITE_STRING: u8 = 0x03;
+const PROOF_LEN: usize = 80; // Gamma (32) || c (16) || s (32)
+
+// Verify `proof` for input `alpha` under the ed25519 public key `public_key`,
+// returning the VRF output (beta) on success
+pub fn verify(public_key: &[u8; 32], alpha: &[u8], proof: &[u8]) -> Option<[u8; 64]> {
+    if proof.len() != PROOF_LEN {
+        return None;
+    }
+    let y = CompressedEdwardsY(*public_key).decompress()?;
+    if y.is_small_order() {
+        return None;
+    }
+    let gamma = CompressedEdwardsY(proof[..32].try_into().ok()?).decompress()?;
+    let mut c_bytes = [0u8; 32];
+    c_bytes[..16].copy_from_slice(&proof[32..48]);
+    let c: Scalar = Option::from(Scalar::from_canonical_bytes(c_bytes))?;
+    let s: Scalar = Option::from(Scalar::from_canonical_bytes(proof[48..].try_into().ok()?))?;
+
+    let h = encode_to_curve(public_key, alpha)?;
+    let u = EdwardsPoint::vartime_double_scalar_mul_basepoint(&-c, &y, &s);
+    let v = s * h - c * gamma;
+
+    if challenge(public_key, &h, &gamma, &u, &v) != proof[32..48] {
+        return None;
+    }
+    Some(proof_to_hash(&gamma))
+}
+
+// Try-and-increment hash to curve, salted with the public key
+fn encode_to_curve(public_key: &[u8; 32], alpha: &[u8]) -> Option<EdwardsPoint> {
+    (0..=u8::MAX).find_map(|ctr| {
+        let hash = Sha512::new()
+            .chain_update([SUITE_STRING, 0x01])
+            .chain_update(public_key)
+            .chain_update(alpha)
+            .chain_update([ctr, 0x00])
+            .finalize();
+        let point = CompressedEdwardsY(hash[..32].try_into().ok()?).decompress()?;
+        Some(point.mul_by_cofactor())
+    })
+}
+
+fn challenge(
+    public_key: &[u8; 32],
+    h: &EdwardsPoint,
+    gamma: &EdwardsPoint,
+    u: &EdwardsPoint,
+    v: &EdwardsPoint,
+) -> [u8; 16] {
+    let hash = Sha512::new()
+        .chain_update([SUITE_STRING, 0x02])
+        .chain_update(public_key)
+        .chain_update(h.compress().as_bytes())
+        .chain_update(gamma.compress().as_bytes())
+        .chain_update(u.compress().as_bytes())
+        .chain_update(v.compress().as_bytes())
+        .chain_update([0x00])
+        .finalize();
+    hash[..16].try_into().unwrap()
+}
+
+fn proof_to_hash(gamma: &EdwardsPoint) -> [u8; 64] {
+    Sha512::new()
+        .chain_update([SUITE_STRING, 0x03])
+        .chain_update(gamma.mul_by_cofactor().compress().as_bytes())
+        .chain_update([0x00])
+        .finalize()
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // RFC 9381, appendix B.3, example 16 (ECVRF-EDWARDS25519-SHA512-TAI, empty alpha)
+    const PUBLIC_KEY: &str = "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a";
+    const PROOF: &str = "8657106690b5526245a92b003bb079ccd1a92130477671f6fc01ad16f26f723f26f8a57ccaed74ee1b190bed1f479d9727d2d0f9b005a6e456a35d4fb0daab1268a1b0db10836d9826a528ca76567805";
+    const OUTPUT: &str = "90cf1df3b703cce59e2a35b925d411164068269d7b2d29f3301c03dd757876ff66b71dda49d2de59d03450451af026798e8f81cd2e333de5cdf4f3e140fdd8ae";
+
+    fn public_key() -> [u8; 32] {
+        hex::decode(PUBLIC_KEY).unwrap().try_into().unwrap()
+    }
+
+    #[test]
+    fn verify_accepts_the_rfc_vector() {
+        let output = verify(&public_key(), b"", &hex::decode(PROOF).unwrap()).unwrap();
+        assert_eq!(hex::encode(output), OUTPUT);
+    }
+
+    #[test]
+    fn verify_rejects_a_tampered_proof() {
+        let proof = hex::decode(PROOF).unwrap();
+        for index in [0, 32, 48, PROOF_LEN - 1] {
+            let mut tampered = proof.clone();
+            tampered[index] ^= 0x01;
+            assert_eq!(verify(&public_key(), b"", &tampered), None, "byte {}", index);
+        }
+        assert_eq!(verify(&public_key(), b"\x72", &proof), None);
+        assert_eq!(verify(&public_key(), b"", &proof[..PROOF_LEN - 1]), None);
+    }
+}
@@ -1,10 +1,20 @@
 use crate::*;
+use crypto_bigint::{Encoding, U256};
+use sha2::{Digest, Sha256};
+use sha3::Sha3_256;
 use external::{mpc_contract, SignRequest};
 
-const GAS: Gas = Gas::from_tgas(50);
-const ATTACHED_DEPOSIT: NearToken = NearToken::from_yoctonear(1);
+// Defaults for the owner-settable mpc_gas / mpc_deposit
+pub const DEFAULT_MPC_GAS: Gas = Gas::from_tgas(50);
+pub const DEFAULT_MPC_DEPOSIT: NearToken = NearToken::from_yoctonear(1);
 
-pub fn get_sig(payload: Vec<u8>, path: String, key_version: u32) -> Promise {
+pub fn get_sig(
+    payload: Vec<u8>,
+    path: String,
+    key_version: u32,
+    gas: Gas,
+    deposit: NearToken,
+) -> Promise {
     let request = SignRequest {
         payload: utils::vec_to_fixed(payload),
         path,
@@ -18,7 +28,148 @@ pub fn get_sig(payload: Vec<u8>, path: String, key_version: u32) -> Promise {
     };
 
     mpc_contract::ext(mpc_contract_id.parse().unwrap())
-        .with_static_gas(GAS)
-        .with_attached_deposit(ATTACHED_DEPOSIT)
+        .with_static_gas(gas)
+        .with_attached_deposit(deposit)
         .sign(request)
 }
+
+// Hex sha256 over the signature's R point, s scalar and recovery id, for logs and outcome records
+pub fn signature_hash(signature: &SignResult) -> String {
+    let hash = Sha256::new()
+        .chain_update(signature.big_r.affine_point.as_bytes())
+        .chain_update(signature.s.scalar.as_bytes())
+        .chain_update([signature.recovery_id])
+        .finalize();
+    hex::encode(hash)
+}
+
+// Whether `signature` (r || s || v, 65 bytes) over the 32-byte `payload` recovers to
+// `expected_pubkey`, a 33-byte compressed or 64/65-byte uncompressed secp256k1 key.
+// v may be the raw recovery id or Ethereum-style (27/28)
+pub fn verify_sig(payload: &[u8], signature: &[u8], expected_pubkey: &[u8]) -> bool {
+    let (Ok(hash), Some((&v, rs))) = (<[u8; 32]>::try_from(payload), signature.split_last()) else {
+        return false;
+    };
+    let Ok(rs) = <[u8; 64]>::try_from(rs) else {
+        return false;
+    };
+    let v = if v >= 27 { v - 27 } else { v };
+    // The host rejects recovery ids above 3 with a panic rather than None
+    if v > 3 {
+        return false;
+    }
+    let Some(recovered) = env::ecrecover(&hash, &rs, v, false) else {
+        return false;
+    };
+    match expected_pubkey {
+        [0x04, key @ ..] if key.len() == 64 => key == recovered,
+        [prefix, x @ ..] if x.len() == 32 => *prefix == 0x02 | (recovered[63] & 1) && x == &recovered[..32],
+        key => key == recovered,
+    }
+}
+
+// r || s || recovery id of an MPC SignResult, the layout verify_sig takes
+pub fn signature_bytes(signature: &SignResult) -> Option<Vec<u8>> {
+    let big_r = hex::decode(&signature.big_r.affine_point).ok()?;
+    let s = hex::decode(&signature.s.scalar).ok()?;
+    if big_r.len() != 33 || s.len() != 32 {
+        return None;
+    }
+    Some([&big_r[1..], &s, &[signature.recovery_id]].concat())
+}
+
+// MPC (chain signatures v1) key derivation. For a sign request made by `predecessor`:
+//   epsilon = SHA3-256("near-mpc-recovery v0.1.0 epsilon derivation:" || predecessor || "," || path)
+//   derived = root + epsilon * G
+// sign_tx calls the MPC itself, so the predecessor is this contract, not the worker
+const EPSILON_DERIVATION_PREFIX: &str = "near-mpc-recovery v0.1.0 epsilon derivation:";
+const SECP256K1_ORDER: U256 =
+    U256::from_be_hex("FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEBAAEDCE6AF48A03BBFD25E8CD0364141");
+
+// 64-byte uncompressed key (no 0x04 prefix) from the MPC public_key view, "secp256k1:<base58>"
+pub fn parse_public_key(public_key: &str) -> Option<[u8; 64]> {
+    let encoded = public_key.strip_prefix("secp256k1:")?;
+    near_sdk::bs58::decode(encoded).into_vec().ok()?.try_into().ok()
+}
+
+pub fn derive_public_key(root: &[u8; 64], predecessor: &AccountId, path: &str) -> [u8; 64] {
+    let hash = Sha3_256::digest(format!("{}{},{}", EPSILON_DERIVATION_PREFIX, predecessor, path));
+    let epsilon = U256::from_be_slice(&hash).const_rem(&SECP256K1_ORDER).0;
+
+    // No curve arithmetic in the runtime, so let ecrecover compute it: with R = root and
+    // s = r, recovery yields r^-1 * (r * root - h * G), which is root + epsilon * G when
+    // h = -epsilon * r mod n
+    let r = U256::from_be_slice(&root[..32]);
+    require!(r < SECP256K1_ORDER, "Unsupported MPC public key");
+    let h = U256::const_rem_wide(epsilon.mul_wide(&r), &SECP256K1_ORDER).0.neg_mod(&SECP256K1_ORDER);
+    let signature: [u8; 64] = [r.to_be_bytes(), r.to_be_bytes()].concat().try_into().unwrap();
+    env::ecrecover(&h.to_be_bytes(), &signature, root[63] & 1, false)
+        .unwrap_or_else(|| env::panic_str("Key derivation failed"))
+}
+
+// Ethereum-style address of a key: the last 20 bytes of keccak256 of its uncompressed form.
+// On Filecoin this is the 0x form of the key's f410 (delegated) address
+pub fn evm_address(public_key: &[u8; 64]) -> String {
+    format!("0x{}", hex::encode(&env::keccak256_array(public_key)[12..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // secp256k1 known-answer vector: key sha256("vibesflow test key"), payload
+    // sha256("vibesflow dispatch"), low-s signature with recovery id 0
+    const PAYLOAD: &str = "e8adf00cd6a731eb164fc6ff4144f2b1057379c5c2b9519b44d9eb843f0315e0";
+    const SIGNATURE: &str = "620a9c3ae58a23516880d2f50f90121fbbca308991bb72992446c7fedc49f88c\
+                             3edb93bf8ed0d17a84566e828dfedcecf4ba25b092ad3d0526434a22e93271dd00";
+    const PUBLIC_KEY: &str = "44f527643726eb08a039c7f2629d65920e674536a210127526606dfad3f0b48f\
+                              ff428cf2c8be539b29c8286e142a960a69b2f9f1c7ecc1972726f91595891839";
+    const COMPRESSED_PUBLIC_KEY: &str = "0344f527643726eb08a039c7f2629d65920e674536a210127526606dfad3f0b48f";
+
+    fn bytes(hex: &str) -> Vec<u8> {
+        hex::decode(hex).unwrap()
+    }
+
+    #[test]
+    fn verify_sig_accepts_every_key_encoding() {
+        let (payload, signature) = (bytes(PAYLOAD), bytes(SIGNATURE));
+        assert!(verify_sig(&payload, &signature, &bytes(PUBLIC_KEY)));
+        assert!(verify_sig(&payload, &signature, &[&[0x04], bytes(PUBLIC_KEY).as_slice()].concat()));
+        assert!(verify_sig(&payload, &signature, &bytes(COMPRESSED_PUBLIC_KEY)));
+    }
+
+    #[test]
+    fn verify_sig_accepts_ethereum_style_recovery_ids() {
+        let mut signature = bytes(SIGNATURE);
+        signature[64] += 27;
+        assert!(verify_sig(&bytes(PAYLOAD), &signature, &bytes(PUBLIC_KEY)));
+    }
+
+    #[test]
+    fn verify_sig_rejects_mismatches() {
+        let (payload, signature, public_key) = (bytes(PAYLOAD), bytes(SIGNATURE), bytes(PUBLIC_KEY));
+
+        let mut other_payload = payload.clone();
+        other_payload[0] ^= 1;
+        assert!(!verify_sig(&other_payload, &signature, &public_key));
+
+        let mut other_recovery_id = signature.clone();
+        other_recovery_id[64] = 1;
+        assert!(!verify_sig(&payload, &other_recovery_id, &public_key));
+
+        let mut other_parity = bytes(COMPRESSED_PUBLIC_KEY);
+        other_parity[0] = 0x02;
+        assert!(!verify_sig(&payload, &signature, &other_parity));
+    }
+
+    #[test]
+    fn verify_sig_rejects_malformed_input() {
+        let (payload, signature, public_key) = (bytes(PAYLOAD), bytes(SIGNATURE), bytes(PUBLIC_KEY));
+        assert!(!verify_sig(&payload[..31], &signature, &public_key));
+        assert!(!verify_sig(&payload, &signature[..64], &public_key));
+
+        let mut bad_recovery_id = signature.clone();
+        bad_recovery_id[64] = 31;
+        assert!(!verify_sig(&payload, &bad_recovery_id, &public_key));
+    }
+}
@@ -1,4 +1,4 @@
-use near_sdk::ext_contract;
+use near_sdk::{ext_contract, near, AccountId};
 use serde::Serialize;
 
 #[derive(Debug, Serialize)]
@@ -8,8 +8,35 @@ pub struct SignRequest {
     pub key_version: u32,
 }
 
+#[derive(Debug)]
+#[near(serializers = [json])]
+pub struct SignResult {
+    pub big_r: AffinePoint,
+    pub s: Scalar,
+    pub recovery_id: u8,
+}
+
+#[derive(Debug)]
+#[near(serializers = [json])]
+pub struct AffinePoint {
+    pub affine_point: String,
+}
+
+#[derive(Debug)]
+#[near(serializers = [json])]
+pub struct Scalar {
+    pub scalar: String,
+}
+
 #[allow(dead_code)]
 #[ext_contract(mpc_contract)]
 trait MPCContract {
     fn sign(&self, request: SignRequest);
 }
+
+#[allow(dead_code)]
+#[ext_contract(rta_factory)]
+trait RtaFactory {
+    fn finalize(&mut self, rta_id: String, filecoin_master_cid: String);
+    fn check_delegation(&self, rta_id: String, delegate: AccountId) -> bool;
+}
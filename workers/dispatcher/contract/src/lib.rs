@@ -1,19 +1,55 @@
-use hex::{decode, encode};
 use near_sdk::{
     env::{self, block_timestamp},
     near, require,
-    store::{IterableMap, IterableSet},
-    AccountId, Gas, NearToken, PanicOnDefault, Promise,
+    store::{IterableMap, IterableSet, LookupMap, Vector},
+    AccountId, Gas, NearToken, PanicOnDefault, Promise, PromiseError,
 };
+use external::SignResult;
+use std::collections::BTreeMap;
 
 mod ecdsa;
+mod events;
 mod external;
+mod migrate;
+#[cfg(test)]
+mod tests;
 mod utils;
 
-// Filecoin Calibration chain configuration
+// Filecoin Calibration chain configuration, used until the owner calls set_filecoin_config
 const FILECOIN_CALIBRATION_CHAIN_ID: u64 = 314159;
 const FILECOIN_RPC_URL: &str = "https://api.calibration.node.glif.io/rpc/v1";
 
+// Collateral issued longer ago than this is rejected at registration unless the owner changes it.
+// Age is taken from the Intel-signed issueDate, so it has to allow for how often PCS reissues it
+const DEFAULT_MAX_ATTESTATION_AGE_NS: u64 = 7 * 24 * 60 * 60 * 1_000_000_000; // 7 days
+
+// Upper bound on records returned by one paged view call
+const MAX_DISPATCH_PAGE_SIZE: u64 = 100;
+
+// Most recent sign_callback outcomes kept per worker
+const MAX_SIGN_OUTCOMES_PER_WORKER: usize = 20;
+const SIGN_CALLBACK_GAS: Gas = Gas::from_tgas(10);
+const RTA_FINALIZE_GAS: Gas = Gas::from_tgas(30);
+const CHECK_DELEGATION_GAS: Gas = Gas::from_tgas(10);
+const RECORD_DISPATCH_CALLBACK_GAS: Gas = Gas::from_tgas(15);
+
+// Role reported for workers whose codehash has no role set with set_codehash_role
+const DEFAULT_WORKER_ROLE: &str = "dispatcher";
+
+// sign_tx calls each worker may make per window unless the owner changes it
+const DEFAULT_MAX_SIGNS_PER_WINDOW: u32 = 30;
+const DEFAULT_SIGN_WINDOW_NS: u64 = 60 * 1_000_000_000; // 1 minute
+
+// Whether the Filecoin storage deal for a dispatched chunk has landed
+#[near(serializers = [json, borsh])]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DealStatus {
+    #[default]
+    Pending,
+    Active,
+    Failed,
+}
+
 // Dispatcher tracking
 #[near(serializers = [json, borsh])]
 #[derive(Clone)]
@@ -22,6 +58,47 @@ pub struct DispatchRecord {
     pub rta_id: String,
     pub filecoin_cid: String,
     pub timestamp: u64,
+    pub sequence: u64, // position of the chunk within the stream
+    pub deal_status: DealStatus,
+    pub dispatch_seq: u64, // global recording order; orders dispatches that share a block timestamp
+}
+
+// Result of one MPC sign call, as observed by sign_callback
+#[near(serializers = [json, borsh])]
+#[derive(Clone)]
+pub struct SignOutcome {
+    pub rta_id: String,
+    pub chunk_id: String,
+    pub success: bool,
+    pub signature_hash: Option<String>, // hex sha256 of the signature; None if the MPC call failed
+    pub timestamp: u64,
+}
+
+// Aggregate view over dispatch records; timestamps are 0 when there are no records
+#[near(serializers = [json])]
+#[derive(Default)]
+pub struct DispatchStats {
+    pub total: u64,
+    pub pending: u64,
+    pub active: u64,
+    pub failed: u64,
+    pub first_ts: u64,
+    pub last_ts: u64,
+}
+
+impl DispatchStats {
+    fn add(&mut self, record: &DispatchRecord) {
+        if self.total == 0 || record.timestamp < self.first_ts {
+            self.first_ts = record.timestamp;
+        }
+        self.last_ts = self.last_ts.max(record.timestamp);
+        self.total += 1;
+        match record.deal_status {
+            DealStatus::Pending => self.pending += 1,
+            DealStatus::Active => self.active += 1,
+            DealStatus::Failed => self.failed += 1,
+        }
+    }
 }
 
 #[near(serializers = [json, borsh])]
@@ -29,6 +106,32 @@ pub struct DispatchRecord {
 pub struct Worker {
     checksum: String,
     codehash: String,
+    attested_at: Option<u64>, // ns; issueDate of the collateral, None for development registrations
+    last_seen: u64,           // ns; registration or latest heartbeat
+}
+
+// Registration probe for frontends; codehash is None when the account is not registered
+#[near(serializers = [json])]
+pub struct WorkerStatus {
+    pub registered: bool,
+    pub codehash: Option<String>,
+    pub codehash_approved: bool,
+}
+
+// What register_worker / register_worker_dev recorded, so the worker can confirm its onboarding
+#[near(serializers = [json])]
+pub struct WorkerRegistration {
+    pub account_id: AccountId,
+    pub codehash: String,
+    pub role: String, // set per codehash by the owner, DEFAULT_WORKER_ROLE otherwise
+    pub registered_at: u64, // ns
+}
+
+// Filecoin network the dispatcher workers submit to
+#[near(serializers = [json])]
+pub struct FilecoinConfig {
+    pub chain_id: u64,
+    pub rpc_url: String,
 }
 
 #[near(contract_state)]
@@ -39,6 +142,29 @@ pub struct Contract {
     pub worker_by_account_id: IterableMap<AccountId, Worker>,
     // Minimal dispatcher functionality
     pub dispatch_records: IterableMap<String, Vec<DispatchRecord>>, // rta_id -> dispatches
+    pub max_attestation_age_ns: u64,
+    pub mpc_gas: Gas,
+    pub mpc_deposit: NearToken,
+    pub sign_outcomes: LookupMap<AccountId, Vec<SignOutcome>>, // worker -> recent outcomes
+    pub worker_nonces: IterableMap<AccountId, u64>, // worker -> next unused Filecoin tx nonce
+    pub filecoin_chain_id: u64,
+    pub filecoin_rpc_url: String,
+    pub rta_factory: Option<AccountId>, // RTAv2 contract used for delegation checks and finalize
+    pub pending_owner: Option<AccountId>, // proposed by propose_owner, awaiting accept_owner
+    pub paused: bool, // incident kill switch for signing, dispatch recording and registration
+    pub dispatch_seq: u64, // dispatch_seq the next recorded dispatch gets
+    pub worker_paths: IterableMap<AccountId, IterableSet<String>>, // worker -> derivation paths it may sign under
+    pub max_signs_per_window: u32,
+    pub sign_window_ns: u64,
+    pub mpc_public_key: Option<String>, // MPC root key ("secp256k1:<base58>") for derive_filecoin_address
+    pub dispatches_by_worker: IterableMap<AccountId, Vector<(String, u64)>>, // worker -> (rta_id, sequence) it recorded, oldest first
+    pub expected_compose_hashes: IterableSet<String>, // approved app compose hashes; empty disables the check
+    pub codehash_roles: LookupMap<String, String>, // codehash -> role its workers register as
+    pub removed_workers: IterableSet<AccountId>, // removed by the owner; may not register again until readmitted
+    // worker -> (window start ns, sign_tx calls in that window); outlives the Worker record so
+    // deregistering and registering again doesn't reset the window
+    pub sign_windows: LookupMap<AccountId, (u64, u32)>,
+    pub dev_mode: bool, // allows unattested register_worker_dev
 }
 
 #[near]
@@ -51,6 +177,27 @@ impl Contract {
             approved_codehashes: IterableSet::new(b"a"),
             worker_by_account_id: IterableMap::new(b"b"),
             dispatch_records: IterableMap::new(b"c"),
+            max_attestation_age_ns: DEFAULT_MAX_ATTESTATION_AGE_NS,
+            mpc_gas: ecdsa::DEFAULT_MPC_GAS,
+            mpc_deposit: ecdsa::DEFAULT_MPC_DEPOSIT,
+            sign_outcomes: LookupMap::new(b"s"),
+            worker_nonces: IterableMap::new(b"n"),
+            filecoin_chain_id: FILECOIN_CALIBRATION_CHAIN_ID,
+            filecoin_rpc_url: FILECOIN_RPC_URL.to_string(),
+            rta_factory: None,
+            pending_owner: None,
+            paused: false,
+            dispatch_seq: 0,
+            worker_paths: IterableMap::new(b"d"),
+            max_signs_per_window: DEFAULT_MAX_SIGNS_PER_WINDOW,
+            sign_window_ns: DEFAULT_SIGN_WINDOW_NS,
+            mpc_public_key: None,
+            dispatches_by_worker: IterableMap::new(b"w"),
+            expected_compose_hashes: IterableSet::new(b"m"),
+            codehash_roles: LookupMap::new(b"r"),
+            removed_workers: IterableSet::new(b"x"),
+            sign_windows: LookupMap::new(b"q"),
+            dev_mode: false,
         }
     }
 
@@ -60,23 +207,288 @@ impl Contract {
         self.approved_codehashes.insert(codehash);
     }
 
+    // Revoke a codehash; workers registered with it fail require_registered_worker from then on
+    pub fn remove_codehash(&mut self, codehash: String) {
+        self.require_owner();
+        self.approved_codehashes.remove(&codehash);
+    }
+
+    // Batch variants for deploys that ship several worker images; both return how many changed
+    pub fn approve_codehashes(&mut self, codehashes: Vec<String>) -> u32 {
+        self.require_owner();
+        codehashes
+            .into_iter()
+            .filter(|codehash| self.approved_codehashes.insert(codehash.clone()))
+            .count() as u32
+    }
+
+    pub fn remove_codehashes(&mut self, codehashes: Vec<String>) -> u32 {
+        self.require_owner();
+        codehashes
+            .iter()
+            .filter(|codehash| self.approved_codehashes.remove(*codehash))
+            .count() as u32
+    }
+
+    // Pin the compose manifests register_worker accepts. While none are approved only the
+    // codehash is checked; removing the last one turns the check off again
+    pub fn approve_compose_hash(&mut self, compose_hash: String) {
+        self.require_owner();
+        self.expected_compose_hashes.insert(compose_hash);
+    }
+
+    pub fn remove_compose_hash(&mut self, compose_hash: String) {
+        self.require_owner();
+        self.expected_compose_hashes.remove(&compose_hash);
+    }
+
+    pub fn get_expected_compose_hashes(&self) -> Vec<String> {
+        self.expected_compose_hashes.iter().cloned().collect()
+    }
+
+    // Role reported to workers registering under an approved codehash
+    pub fn set_codehash_role(&mut self, codehash: String, role: String) {
+        self.require_owner();
+        require!(self.approved_codehashes.contains(&codehash), "Codehash not approved");
+        self.codehash_roles.insert(codehash, role);
+    }
+
+    pub fn get_codehash_role(&self, codehash: String) -> String {
+        self.codehash_roles
+            .get(&codehash)
+            .cloned()
+            .unwrap_or_else(|| DEFAULT_WORKER_ROLE.to_string())
+    }
+
+    // Per-worker sign_tx budget: at most max_signs_per_window calls every sign_window_ns
+    pub fn set_sign_rate_limit(&mut self, max_signs_per_window: u32, sign_window_ns: u64) {
+        self.require_owner();
+        self.max_signs_per_window = max_signs_per_window;
+        self.sign_window_ns = sign_window_ns;
+    }
+
+    pub fn get_sign_rate_limit(&self) -> (u32, u64) {
+        (self.max_signs_per_window, self.sign_window_ns)
+    }
+
+    // Owner-approved derivation paths per worker; sign_tx only signs under the caller's own
+    // paths, so one worker can't request signatures with another's MPC-derived key
+    pub fn register_path(&mut self, account_id: AccountId, path: String) {
+        self.require_owner();
+        let prefix = [b"e".as_slice(), &env::sha256(account_id.as_bytes())].concat();
+        self.worker_paths
+            .entry(account_id)
+            .or_insert_with(|| IterableSet::new(prefix))
+            .insert(path);
+    }
+
+    pub fn remove_path(&mut self, account_id: AccountId, path: String) {
+        self.require_owner();
+        let Some(paths) = self.worker_paths.get_mut(&account_id) else {
+            return;
+        };
+        paths.remove(&path);
+        if paths.is_empty() {
+            self.worker_paths.remove(&account_id);
+        }
+    }
+
+    pub fn get_worker_paths(&self, account_id: AccountId) -> Vec<String> {
+        self.worker_paths
+            .get(&account_id)
+            .map(|paths| paths.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    // Two-step ownership transfer: the owner proposes, the proposed account accepts
+    pub fn propose_owner(&mut self, new_owner: AccountId) {
+        self.require_owner();
+        self.pending_owner = Some(new_owner);
+    }
+
+    pub fn accept_owner(&mut self) {
+        let predecessor = env::predecessor_account_id();
+        require!(
+            self.pending_owner.as_ref() == Some(&predecessor),
+            "Only the pending owner can accept ownership"
+        );
+        self.owner_id = predecessor;
+        self.pending_owner = None;
+    }
+
+    pub fn get_pending_owner(&self) -> Option<AccountId> {
+        self.pending_owner.clone()
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.require_owner();
+        self.paused = paused;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    // Turning dev mode off also locks out the workers registered through register_worker_dev
+    pub fn set_dev_mode(&mut self, dev_mode: bool) {
+        self.require_owner();
+        self.dev_mode = dev_mode;
+    }
+
+    pub fn is_dev_mode(&self) -> bool {
+        self.dev_mode
+    }
+
+    pub fn set_max_attestation_age_ns(&mut self, max_attestation_age_ns: u64) {
+        self.require_owner();
+        self.max_attestation_age_ns = max_attestation_age_ns;
+    }
+
+    pub fn get_max_attestation_age_ns(&self) -> u64 {
+        self.max_attestation_age_ns
+    }
+
+    pub fn set_filecoin_config(&mut self, chain_id: u64, rpc_url: String) {
+        self.require_owner();
+        self.filecoin_chain_id = chain_id;
+        self.filecoin_rpc_url = rpc_url;
+    }
+
+    pub fn get_filecoin_config(&self) -> FilecoinConfig {
+        FilecoinConfig {
+            chain_id: self.filecoin_chain_id,
+            rpc_url: self.filecoin_rpc_url.clone(),
+        }
+    }
+
+    pub fn set_rta_factory(&mut self, rta_factory: AccountId) {
+        self.require_owner();
+        self.rta_factory = Some(rta_factory);
+    }
+
+    pub fn get_rta_factory(&self) -> Option<AccountId> {
+        self.rta_factory.clone()
+    }
+
+    // Gas and deposit attached to the MPC sign call made by sign_tx
+    pub fn set_mpc_gas(&mut self, mpc_gas: Gas) {
+        self.require_owner();
+        self.mpc_gas = mpc_gas;
+    }
+
+    pub fn set_mpc_deposit(&mut self, mpc_deposit: NearToken) {
+        self.require_owner();
+        self.mpc_deposit = mpc_deposit;
+    }
+
+    pub fn get_mpc_gas(&self) -> Gas {
+        self.mpc_gas
+    }
+
+    pub fn get_mpc_deposit(&self) -> NearToken {
+        self.mpc_deposit
+    }
+
+    // Root key of the MPC contract sign_tx calls, as returned by its public_key view
+    pub fn set_mpc_public_key(&mut self, public_key: String) {
+        self.require_owner();
+        require!(ecdsa::parse_public_key(&public_key).is_some(), "Invalid MPC public key");
+        self.mpc_public_key = Some(public_key);
+    }
+
+    pub fn get_mpc_public_key(&self) -> Option<String> {
+        self.mpc_public_key.clone()
+    }
+
+    // 0x (f410) address that sign_tx signatures under derivation_path recover to, so workers
+    // can fund and reference it without deriving off-chain. See ecdsa::derive_public_key
+    pub fn derive_filecoin_address(&self, derivation_path: String, key_version: u32) -> String {
+        // The MPC has a single root key; other versions have no key to derive from
+        require!(key_version == 0, "Unsupported key_version");
+        let root = self
+            .mpc_public_key
+            .as_deref()
+            .and_then(ecdsa::parse_public_key)
+            .unwrap_or_else(|| env::panic_str("MPC public key not set"));
+        let derived = ecdsa::derive_public_key(&root, &env::current_account_id(), &derivation_path);
+        ecdsa::evm_address(&derived)
+    }
+
     // Get approved codehashes (for worker registration)
     pub fn get_approved_codehashes(&self) -> Vec<String> {
         self.approved_codehashes.iter().cloned().collect()
     }
 
+    // Registered workers per codehash, sorted by codehash. Approved hashes with no workers
+    // show 0; hashes that were revoked while workers still run them are listed too
+    pub fn get_codehash_worker_counts(&self) -> Vec<(String, u32)> {
+        let mut counts: BTreeMap<String, u32> = self
+            .approved_codehashes
+            .iter()
+            .map(|codehash| (codehash.clone(), 0))
+            .collect();
+        for worker in self.worker_by_account_id.values() {
+            *counts.entry(worker.codehash.clone()).or_default() += 1;
+        }
+        counts.into_iter().collect()
+    }
+
     /// Core signing function (from template)
     pub fn sign_tx(
         &mut self,
         payload: Vec<u8>,
         derivation_path: String,
         key_version: u32,
+        rta_id: String,
+        chunk_id: String,
     ) -> Promise {
+        self.require_not_paused();
         // Require registered worker for production security
         self.require_registered_worker();
+        self.consume_sign_quota();
+        require!(
+            self.worker_paths
+                .get(&env::predecessor_account_id())
+                .is_some_and(|paths| paths.contains(&derivation_path)),
+            "Derivation path not registered for worker"
+        );
 
-        // Call the MPC contract to get a signature for the payload
-        ecdsa::get_sig(payload, derivation_path, key_version)
+        // Call the MPC contract to get a signature for the payload, then record how it went
+        ecdsa::get_sig(payload, derivation_path, key_version, self.mpc_gas, self.mpc_deposit).then(
+            Self::ext(env::current_account_id())
+                .with_static_gas(SIGN_CALLBACK_GAS)
+                .sign_callback(env::predecessor_account_id(), rta_id, chunk_id),
+        )
+    }
+
+    // Records the MPC outcome against the worker and passes the signature through to the caller
+    #[private]
+    pub fn sign_callback(
+        &mut self,
+        worker_id: AccountId,
+        rta_id: String,
+        chunk_id: String,
+        #[callback_result] result: Result<SignResult, PromiseError>,
+    ) -> Option<SignResult> {
+        let signature = result.ok();
+        let signature_hash = signature.as_ref().map(ecdsa::signature_hash);
+        match &signature_hash {
+            Some(hash) => env::log_str(&format!("MPC signature for {}/{}: {}", rta_id, chunk_id, hash)),
+            None => env::log_str(&format!("MPC signing failed for {}/{}", rta_id, chunk_id)),
+        }
+
+        let outcomes = self.sign_outcomes.entry(worker_id).or_default();
+        if outcomes.len() >= MAX_SIGN_OUTCOMES_PER_WORKER {
+            outcomes.remove(0);
+        }
+        outcomes.push(SignOutcome {
+            rta_id,
+            chunk_id,
+            success: signature.is_some(),
+            signature_hash,
+            timestamp: env::block_timestamp(),
+        });
+        signature
     }
 
     // Register worker with TEE attestation - MODIFIED to accept pre-verified data from worker
@@ -85,83 +497,371 @@ impl Contract {
         verified_codehash: String,
         worker_account_id: String,
         checksum: String,
-        tee_verification_proof: String, // JSON proof that worker verified TEE
-    ) -> bool {
+        tee_verification_proof: String, // JSON attestation::TeeVerificationProof
+    ) -> WorkerRegistration {
+        self.require_not_paused();
         // Verify the codehash is approved
         require!(self.approved_codehashes.contains(&verified_codehash), "Codehash not approved");
 
         // Verify the caller matches the worker account
         require!(
-            env::predecessor_account_id().to_string() == worker_account_id,
+            env::predecessor_account_id().as_str() == worker_account_id,
             "Caller must match worker account"
         );
 
-        let predecessor = env::predecessor_account_id();
-        self.worker_by_account_id.insert(
-            predecessor,
-            Worker {
-                checksum,
-                codehash: verified_codehash,
-            },
+        // Rejects malformed or stale attestations, and re-derives the codehash rather than
+        // trusting the caller's claim
+        let compose_hashes = &self.expected_compose_hashes;
+        let attestation = attestation::verify_tee_proof(
+            &tee_verification_proof,
+            &worker_account_id,
+            self.max_attestation_age_ns,
+            |compose_hash| compose_hashes.is_empty() || compose_hashes.contains(compose_hash),
         );
+        require!(attestation.codehash == verified_codehash, "Codehash does not match attestation");
 
-        true
+        self.insert_worker(checksum, verified_codehash, Some(attestation.attested_at))
     }
 
     // Development registration
-    pub fn register_worker_dev(&mut self, codehash: String) -> bool {
+    // Development registration without attestation, only while dev_mode is on
+    pub fn register_worker_dev(&mut self, codehash: String) -> WorkerRegistration {
+        self.require_not_paused();
+        require!(self.dev_mode, "Dev registration is disabled");
         // verify the code hashes are approved
         require!(self.approved_codehashes.contains(&codehash), "Codehash not approved");
 
+        self.insert_worker("dev".to_string(), codehash, None)
+    }
+
+    // Re-attesting workers deregister_worker first
+    fn insert_worker(&mut self, checksum: String, codehash: String, attested_at: Option<u64>) -> WorkerRegistration {
         let predecessor = env::predecessor_account_id();
+        require!(
+            !self.worker_by_account_id.contains_key(&predecessor),
+            "Worker already registered"
+        );
+        require!(!self.removed_workers.contains(&predecessor), "Worker was removed by the owner");
+        events::WorkerEvent::new(&predecessor, &codehash).emit_register();
+        let registered_at = block_timestamp();
         self.worker_by_account_id.insert(
-            predecessor,
+            predecessor.clone(),
             Worker {
-                checksum: "dev".to_string(),
-                codehash,
+                checksum,
+                codehash: codehash.clone(),
+                attested_at,
+                last_seen: registered_at,
             },
         );
 
-        true
+        WorkerRegistration {
+            account_id: predecessor,
+            role: self.get_codehash_role(codehash.clone()),
+            codehash,
+            registered_at,
+        }
+    }
+
+    // Remove a (possibly compromised) worker; it can't register again until readmit_worker
+    pub fn remove_worker(&mut self, worker_account_id: AccountId) {
+        self.require_owner();
+        let worker = self
+            .worker_by_account_id
+            .remove(&worker_account_id)
+            .unwrap_or_else(|| env::panic_str("Worker not registered"));
+        events::WorkerEvent::new(&worker_account_id, &worker.codehash).emit_remove();
+        self.removed_workers.insert(worker_account_id);
+    }
+
+    // Lift a remove_worker ban so the account can register again
+    pub fn readmit_worker(&mut self, worker_account_id: AccountId) {
+        self.require_owner();
+        require!(self.removed_workers.remove(&worker_account_id), "Worker was not removed");
+    }
+
+    pub fn get_removed_workers(&self) -> Vec<AccountId> {
+        self.removed_workers.iter().cloned().collect()
+    }
+
+    // Let a worker remove its own registration
+    pub fn deregister_worker(&mut self) {
+        let predecessor = env::predecessor_account_id();
+        let worker = self
+            .worker_by_account_id
+            .remove(&predecessor)
+            .unwrap_or_else(|| env::panic_str("Worker not registered"));
+        events::WorkerEvent::new(&predecessor, &worker.codehash).emit_remove();
+    }
+
+    // Liveness ping; workers call this periodically so get_stale_workers can spot dead ones
+    pub fn heartbeat(&mut self) {
+        self.require_registered_worker();
+        if let Some(worker) = self.worker_by_account_id.get_mut(&env::predecessor_account_id()) {
+            worker.last_seen = block_timestamp();
+        }
+    }
+
+    // Workers that have not registered or sent a heartbeat within the last `max_idle_ns`
+    pub fn get_stale_workers(&self, max_idle_ns: u64) -> Vec<AccountId> {
+        let now = block_timestamp();
+        self.worker_by_account_id
+            .iter()
+            .filter(|(_, worker)| now.saturating_sub(worker.last_seen) > max_idle_ns)
+            .map(|(account_id, _)| account_id.clone())
+            .collect()
+    }
+
+    // Reserve the caller's next Filecoin tx nonce; call before building the payload passed to sign_tx
+    // so concurrent sign requests from one worker never share a nonce
+    pub fn next_nonce(&mut self) -> u64 {
+        self.require_registered_worker();
+        let nonce = self.worker_nonces.entry(env::predecessor_account_id()).or_insert(0);
+        let reserved = *nonce;
+        *nonce += 1;
+        reserved
     }
 
     // MINIMAL dispatcher-specific functionality - record dispatches to Filecoin
+    // Records are kept sorted by sequence; each sequence can only be recorded once per RTA.
+    // Returns false without changes if the chunk was already recorded (e.g. a retried call)
     pub fn record_dispatch(
         &mut self,
         rta_id: String,
         chunk_id: String,
         filecoin_cid: String,
-    ) {
+        sequence: u64,
+    ) -> bool {
+        self.require_not_paused();
+        self.require_registered_worker();
+        self.internal_record_dispatch(env::predecessor_account_id(), rta_id, chunk_id, filecoin_cid, sequence)
+    }
+
+    // Like record_dispatch, but only records once the RTA factory confirms the caller is delegated
+    // on `rta_id`; resolves to the record_dispatch result
+    pub fn record_dispatch_verified(
+        &mut self,
+        rta_id: String,
+        chunk_id: String,
+        filecoin_cid: String,
+        sequence: u64,
+    ) -> Promise {
+        self.require_not_paused();
         self.require_registered_worker();
+        let factory = self
+            .rta_factory
+            .clone()
+            .unwrap_or_else(|| env::panic_str("RTA factory not configured"));
+
+        external::rta_factory::ext(factory)
+            .with_static_gas(CHECK_DELEGATION_GAS)
+            .check_delegation(rta_id.clone(), env::predecessor_account_id())
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(RECORD_DISPATCH_CALLBACK_GAS)
+                    .on_delegation_checked(env::predecessor_account_id(), rta_id, chunk_id, filecoin_cid, sequence),
+            )
+    }
+
+    #[private]
+    pub fn on_delegation_checked(
+        &mut self,
+        worker_id: AccountId,
+        rta_id: String,
+        chunk_id: String,
+        filecoin_cid: String,
+        sequence: u64,
+        #[callback_result] delegated: Result<bool, PromiseError>,
+    ) -> bool {
+        require!(
+            matches!(delegated, Ok(true)),
+            "Worker is not delegated on this RTA"
+        );
+        self.internal_record_dispatch(worker_id, rta_id, chunk_id, filecoin_cid, sequence)
+    }
+
+    fn internal_record_dispatch(
+        &mut self,
+        worker_id: AccountId,
+        rta_id: String,
+        chunk_id: String,
+        filecoin_cid: String,
+        sequence: u64,
+    ) -> bool {
+        let mut records = self.dispatch_records.get(&rta_id).cloned().unwrap_or_default();
+        if records.iter().any(|r| r.chunk_id == chunk_id) {
+            return false;
+        }
 
         let record = DispatchRecord {
             chunk_id,
             rta_id: rta_id.clone(),
             filecoin_cid,
             timestamp: block_timestamp(),
+            sequence,
+            deal_status: DealStatus::Pending,
+            dispatch_seq: self.dispatch_seq,
         };
 
-        let mut records = self.dispatch_records.get(&rta_id).cloned().unwrap_or_default();
-        records.push(record);
-        self.dispatch_records.insert(rta_id, records);
+        match records.binary_search_by_key(&sequence, |r| r.sequence) {
+            Ok(_) => env::panic_str("Sequence already recorded"),
+            Err(index) => records.insert(index, record),
+        }
+        self.dispatch_records.insert(rta_id.clone(), records);
+        let prefix = [b"v".as_slice(), &env::sha256(worker_id.as_bytes())].concat();
+        self.dispatches_by_worker
+            .entry(worker_id)
+            .or_insert_with(|| Vector::new(prefix))
+            .push((rta_id, sequence));
+        self.dispatch_seq += 1;
+        true
+    }
+
+    // Report the outcome of the Filecoin deal for a recorded chunk
+    pub fn update_deal_status(&mut self, rta_id: String, chunk_id: String, status: DealStatus) {
+        self.require_registered_worker();
+
+        let records = self
+            .dispatch_records
+            .get_mut(&rta_id)
+            .unwrap_or_else(|| env::panic_str("No dispatches recorded for RTA"));
+        let record = records
+            .iter_mut()
+            .find(|r| r.chunk_id == chunk_id)
+            .unwrap_or_else(|| env::panic_str("Dispatch not found"));
+        record.deal_status = status;
     }
 
-    // Get dispatch records for an RTA
+    // Push the master CID to the RTAv2 factory once every chunk is dispatched. The factory only
+    // accepts this if the RTA owner has delegated finalize rights to this contract's account
+    pub fn finalize_rta_on_factory(&mut self, factory: AccountId, rta_id: String, master_cid: String) -> Promise {
+        self.require_registered_worker();
+        require!(
+            self.rta_factory.as_ref() == Some(&factory),
+            "Factory is not the configured RTA factory"
+        );
+
+        external::rta_factory::ext(factory)
+            .with_static_gas(RTA_FINALIZE_GAS)
+            .finalize(rta_id, master_cid)
+    }
+
+    // Get dispatch records for an RTA, ordered by sequence
     pub fn get_rta_dispatches(&self, rta_id: String) -> Vec<DispatchRecord> {
         self.dispatch_records.get(&rta_id).cloned().unwrap_or_default()
     }
 
+    // Get dispatch records for an RTA in the order they were recorded
+    pub fn get_rta_dispatches_by_seq(&self, rta_id: String) -> Vec<DispatchRecord> {
+        let mut records = self.get_rta_dispatches(rta_id);
+        records.sort_unstable_by_key(|r| r.dispatch_seq);
+        records
+    }
+
+    // Page through dispatch records for an RTA, ordered by sequence
+    pub fn get_rta_dispatches_paged(&self, rta_id: String, from_index: u64, limit: u64) -> Vec<DispatchRecord> {
+        let limit = limit.min(MAX_DISPATCH_PAGE_SIZE) as usize;
+        self.dispatch_records
+            .get(&rta_id)
+            .map(|records| records.iter().skip(from_index as usize).take(limit).cloned().collect())
+            .unwrap_or_default()
+    }
+
+    // Page through the dispatches a worker recorded across all RTAs, oldest first
+    pub fn get_worker_dispatches(&self, worker: AccountId, from_index: u64, limit: u64) -> Vec<DispatchRecord> {
+        let limit = limit.min(MAX_DISPATCH_PAGE_SIZE) as usize;
+        let Some(dispatches) = self.dispatches_by_worker.get(&worker) else {
+            return Vec::new();
+        };
+        dispatches
+            .iter()
+            .skip(from_index as usize)
+            .take(limit)
+            .filter_map(|(rta_id, sequence)| {
+                let records = self.dispatch_records.get(rta_id)?;
+                let index = records.binary_search_by_key(sequence, |r| r.sequence).ok()?;
+                Some(records[index].clone())
+            })
+            .collect()
+    }
+
+    pub fn get_worker_dispatch_count(&self, worker: AccountId) -> u64 {
+        self.dispatches_by_worker.get(&worker).map_or(0, |dispatches| dispatches.len() as u64)
+    }
+
+    // Dispatches whose Filecoin deal has not been confirmed yet, ordered by sequence
+    pub fn get_pending_dispatches(&self, rta_id: String) -> Vec<DispatchRecord> {
+        self.dispatch_records
+            .get(&rta_id)
+            .map(|records| {
+                records
+                    .iter()
+                    .filter(|r| r.deal_status == DealStatus::Pending)
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn get_dispatch_stats(&self, rta_id: String) -> DispatchStats {
+        let mut stats = DispatchStats::default();
+        for record in self.dispatch_records.get(&rta_id).into_iter().flatten() {
+            stats.add(record);
+        }
+        stats
+    }
+
+    // Same as get_dispatch_stats, summed over every RTA
+    pub fn get_global_dispatch_stats(&self) -> DispatchStats {
+        let mut stats = DispatchStats::default();
+        for record in self.dispatch_records.values().flatten() {
+            stats.add(record);
+        }
+        stats
+    }
+
+    pub fn get_dispatch_count(&self, rta_id: String) -> u64 {
+        self.dispatch_records.get(&rta_id).map_or(0, |records| records.len() as u64)
+    }
+
     // Check if worker is registered
     pub fn is_worker_registered(&self, worker_account_id: AccountId) -> bool {
         self.worker_by_account_id.contains_key(&worker_account_id)
     }
 
     // View functions
-    pub fn get_worker(&self, account_id: AccountId) -> Worker {
-        self.worker_by_account_id
-            .get(&account_id)
-            .unwrap()
-            .to_owned()
+    pub fn get_worker(&self, account_id: AccountId) -> Option<Worker> {
+        self.worker_by_account_id.get(&account_id).cloned()
+    }
+
+    // Safe to call for unregistered accounts; codehash_approved reflects the current allowlist
+    pub fn get_worker_status(&self, account_id: AccountId) -> WorkerStatus {
+        let codehash = self.worker_by_account_id.get(&account_id).map(|worker| worker.codehash.clone());
+        WorkerStatus {
+            registered: codehash.is_some(),
+            codehash_approved: codehash.as_ref().is_some_and(|codehash| self.approved_codehashes.contains(codehash)),
+            codehash,
+        }
+    }
+
+    // Next nonce next_nonce would hand out to the worker
+    pub fn get_nonce(&self, account_id: AccountId) -> u64 {
+        self.worker_nonces.get(&account_id).copied().unwrap_or(0)
+    }
+
+    // Lets a worker confirm an MPC SignResult recovers to its derived public key (hex,
+    // compressed or uncompressed) before broadcasting the signed Filecoin transaction
+    pub fn verify_signature(&self, payload: Vec<u8>, signature: SignResult, expected_pubkey: String) -> bool {
+        let (Some(signature), Ok(expected_pubkey)) =
+            (ecdsa::signature_bytes(&signature), hex::decode(expected_pubkey))
+        else {
+            return false;
+        };
+        ecdsa::verify_sig(&payload, &signature, &expected_pubkey)
+    }
+
+    // Last MPC signing outcomes for a worker, oldest first
+    pub fn get_sign_outcomes(&self, account_id: AccountId) -> Vec<SignOutcome> {
+        self.sign_outcomes.get(&account_id).cloned().unwrap_or_default()
     }
 
     // Get contract owner
@@ -174,8 +874,41 @@ impl Contract {
         require!(env::predecessor_account_id() == self.owner_id);
     }
 
+    fn require_not_paused(&self) {
+        require!(!self.paused, "Contract paused");
+    }
+
+    // Counts a sign_tx call against the caller's window, starting a new window once the
+    // current one has elapsed
+    fn consume_sign_quota(&mut self) {
+        let now = env::block_timestamp();
+        let (max_signs, window_ns) = (self.max_signs_per_window, self.sign_window_ns);
+        let (window_start, count) = self
+            .sign_windows
+            .entry(env::predecessor_account_id())
+            .or_insert((0, 0));
+        if now.saturating_sub(*window_start) >= window_ns {
+            *window_start = now;
+            *count = 0;
+        }
+        require!(*count < max_signs, "Rate limit exceeded");
+        *count += 1;
+    }
+
     fn require_registered_worker(&self) {
-        let worker = self.get_worker(env::predecessor_account_id());
-        require!(self.approved_codehashes.contains(&worker.codehash));
+        let predecessor = env::predecessor_account_id();
+        let worker = self
+            .worker_by_account_id
+            .get(&predecessor)
+            .unwrap_or_else(|| env::panic_str("Worker not registered"));
+        require!(
+            self.approved_codehashes.contains(&worker.codehash),
+            "Worker codehash not approved"
+        );
+        // Dev workers never attested, so they only act while dev mode is on
+        require!(
+            worker.attested_at.is_some() || self.dev_mode,
+            "Dev registration is disabled"
+        );
     }
 }
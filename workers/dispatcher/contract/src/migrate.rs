@@ -0,0 +1,135 @@
+// State migration from the layout the dispatcher was first deployed with
+use crate::*;
+
+#[near(serializers = [borsh])]
+struct OldWorker {
+    checksum: String,
+    codehash: String,
+}
+
+#[near(serializers = [borsh])]
+struct OldDispatchRecord {
+    chunk_id: String,
+    rta_id: String,
+    filecoin_cid: String,
+    timestamp: u64,
+}
+
+#[near(serializers = [borsh])]
+struct OldContract {
+    owner_id: AccountId,
+    approved_codehashes: IterableSet<String>,
+    worker_by_account_id: IterableMap<AccountId, OldWorker>,
+    dispatch_records: IterableMap<String, Vec<OldDispatchRecord>>,
+}
+
+#[near]
+impl Contract {
+    // Rewrites workers and dispatch records into their current layout in one call, so
+    // their total must fit in its gas. Old dispatches get their recording position as
+    // sequence, and workers count as seen at migration so they aren't reported stale at once.
+    // Old workers carry no attestation, so they act as dev workers until they re-attest
+    #[private]
+    #[init(ignore_state)]
+    pub fn migrate() -> Self {
+        let mut old: OldContract =
+            env::state_read().unwrap_or_else(|| env::panic_str("Contract state not found"));
+        let old_workers: Vec<_> = old.worker_by_account_id.drain().collect();
+        let old_records: Vec<_> = old.dispatch_records.drain().collect();
+        // Flush the removals before the new maps rewrite the same prefixes
+        drop(old.worker_by_account_id);
+        drop(old.dispatch_records);
+
+        let mut contract = Self::init(old.owner_id);
+        contract.approved_codehashes = old.approved_codehashes;
+        let now = block_timestamp();
+        for (account_id, worker) in old_workers {
+            let worker = Worker {
+                checksum: worker.checksum,
+                codehash: worker.codehash,
+                attested_at: None,
+                last_seen: now,
+            };
+            contract.worker_by_account_id.insert(account_id, worker);
+        }
+        for (rta_id, records) in old_records {
+            let records = records
+                .into_iter()
+                .zip(0..)
+                .map(|(record, sequence)| {
+                    let record = DispatchRecord {
+                        chunk_id: record.chunk_id,
+                        rta_id: record.rta_id,
+                        filecoin_cid: record.filecoin_cid,
+                        timestamp: record.timestamp,
+                        sequence,
+                        deal_status: DealStatus::Pending,
+                        dispatch_seq: contract.dispatch_seq,
+                    };
+                    contract.dispatch_seq += 1;
+                    record
+                })
+                .collect();
+            contract.dispatch_records.insert(rta_id, records);
+        }
+        contract
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::testing_env;
+
+    fn old_record(chunk_id: &str, timestamp: u64) -> OldDispatchRecord {
+        OldDispatchRecord {
+            chunk_id: chunk_id.to_string(),
+            rta_id: "rta".to_string(),
+            filecoin_cid: format!("cid-{}", chunk_id),
+            timestamp,
+        }
+    }
+
+    #[test]
+    fn migrate_keeps_workers_and_dispatches() {
+        testing_env!(VMContextBuilder::new().block_timestamp(42).build());
+        let mut old = OldContract {
+            owner_id: accounts(0),
+            approved_codehashes: IterableSet::new(b"a"),
+            worker_by_account_id: IterableMap::new(b"b"),
+            dispatch_records: IterableMap::new(b"c"),
+        };
+        old.approved_codehashes.insert("c0dehash".to_string());
+        let worker = OldWorker { checksum: "sum".to_string(), codehash: "c0dehash".to_string() };
+        old.worker_by_account_id.insert(accounts(1), worker);
+        old.dispatch_records.insert("rta".to_string(), vec![old_record("c0", 10), old_record("c1", 20)]);
+        old.approved_codehashes.flush();
+        old.worker_by_account_id.flush();
+        old.dispatch_records.flush();
+        env::state_write(&old);
+
+        let contract = Contract::migrate();
+
+        assert_eq!(contract.get_owner(), accounts(0));
+        assert_eq!(contract.get_approved_codehashes(), vec!["c0dehash".to_string()]);
+        let worker = contract.get_worker(accounts(1)).unwrap();
+        assert_eq!((worker.checksum.as_str(), worker.codehash.as_str()), ("sum", "c0dehash"));
+        assert_eq!((worker.attested_at, worker.last_seen), (None, 42));
+
+        let records = contract.get_rta_dispatches("rta".to_string());
+        assert_eq!(records.len(), 2);
+        assert_eq!((records[0].chunk_id.as_str(), records[0].sequence, records[0].timestamp), ("c0", 0, 10));
+        assert_eq!((records[1].chunk_id.as_str(), records[1].sequence, records[1].timestamp), ("c1", 1, 20));
+        assert_eq!(records[1].filecoin_cid, "cid-c1");
+        assert!(records.iter().all(|r| r.deal_status == DealStatus::Pending));
+        assert_eq!(contract.dispatch_seq, 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "Contract state not found")]
+    fn migrate_without_state_fails() {
+        testing_env!(VMContextBuilder::new().build());
+        Contract::migrate();
+    }
+}
@@ -0,0 +1,205 @@
+use super::*;
+use near_sdk::test_utils::{accounts, VMContextBuilder};
+use near_sdk::testing_env;
+
+const CODEHASH: &str = "c0dehash";
+const PATH: &str = "filecoin-1";
+
+fn set_caller(predecessor: AccountId) {
+    testing_env!(VMContextBuilder::new()
+        .current_account_id("dispatcher.near".parse().unwrap())
+        .predecessor_account_id(predecessor.clone())
+        .signer_account_id(predecessor)
+        .build());
+}
+
+/// Contract owned by `accounts(0)` with `CODEHASH` approved and dev registration enabled.
+fn setup() -> Contract {
+    set_caller(accounts(0));
+    let mut contract = Contract::init(accounts(0));
+    contract.approve_codehash(CODEHASH.to_string());
+    contract.set_dev_mode(true);
+    contract
+}
+
+/// Dev-registers `worker` under `CODEHASH` and lets it sign under `PATH`.
+fn register(contract: &mut Contract, worker: AccountId) {
+    set_caller(worker.clone());
+    contract.register_worker_dev(CODEHASH.to_string());
+    set_caller(accounts(0));
+    contract.register_path(worker, PATH.to_string());
+}
+
+fn sign(contract: &mut Contract, worker: AccountId) {
+    set_caller(worker);
+    let _ = contract.sign_tx(vec![0; 32], PATH.to_string(), 0, "rta".to_string(), "c0".to_string());
+}
+
+#[test]
+fn sign_tx_allows_the_window_quota() {
+    let mut contract = setup();
+    register(&mut contract, accounts(1));
+    set_caller(accounts(0));
+    contract.set_sign_rate_limit(2, DEFAULT_SIGN_WINDOW_NS);
+
+    sign(&mut contract, accounts(1));
+    sign(&mut contract, accounts(1));
+    assert_eq!(contract.sign_windows.get(&accounts(1)).unwrap().1, 2);
+}
+
+#[test]
+#[should_panic(expected = "Rate limit exceeded")]
+fn sign_tx_rejects_one_over_the_window_quota() {
+    let mut contract = setup();
+    register(&mut contract, accounts(1));
+    set_caller(accounts(0));
+    contract.set_sign_rate_limit(2, DEFAULT_SIGN_WINDOW_NS);
+
+    sign(&mut contract, accounts(1));
+    sign(&mut contract, accounts(1));
+    sign(&mut contract, accounts(1));
+}
+
+#[test]
+#[should_panic(expected = "Rate limit exceeded")]
+fn registering_again_keeps_the_sign_window() {
+    let mut contract = setup();
+    register(&mut contract, accounts(1));
+    set_caller(accounts(0));
+    contract.set_sign_rate_limit(2, DEFAULT_SIGN_WINDOW_NS);
+
+    sign(&mut contract, accounts(1));
+    sign(&mut contract, accounts(1));
+    set_caller(accounts(1));
+    contract.deregister_worker();
+    contract.register_worker_dev(CODEHASH.to_string());
+    sign(&mut contract, accounts(1));
+}
+
+#[test]
+#[should_panic(expected = "Worker already registered")]
+fn registering_twice_is_rejected() {
+    let mut contract = setup();
+    register(&mut contract, accounts(1));
+    set_caller(accounts(1));
+    contract.register_worker_dev(CODEHASH.to_string());
+}
+
+#[test]
+#[should_panic(expected = "Worker was removed by the owner")]
+fn removed_worker_cannot_register_again() {
+    let mut contract = setup();
+    register(&mut contract, accounts(1));
+    set_caller(accounts(0));
+    contract.remove_worker(accounts(1));
+    assert!(!contract.is_worker_registered(accounts(1)));
+
+    set_caller(accounts(1));
+    contract.register_worker_dev(CODEHASH.to_string());
+}
+
+#[test]
+fn readmitted_worker_can_register_again() {
+    let mut contract = setup();
+    register(&mut contract, accounts(1));
+    set_caller(accounts(0));
+    contract.remove_worker(accounts(1));
+    contract.readmit_worker(accounts(1));
+
+    set_caller(accounts(1));
+    contract.register_worker_dev(CODEHASH.to_string());
+    assert!(contract.is_worker_registered(accounts(1)));
+}
+
+#[test]
+fn deregistered_worker_can_register_again() {
+    let mut contract = setup();
+    register(&mut contract, accounts(1));
+    set_caller(accounts(1));
+    contract.deregister_worker();
+    contract.register_worker_dev(CODEHASH.to_string());
+    assert!(contract.is_worker_registered(accounts(1)));
+}
+
+fn record(contract: &mut Contract, worker: AccountId, chunk_id: &str, sequence: u64) -> bool {
+    set_caller(worker);
+    contract.record_dispatch("rta".to_string(), chunk_id.to_string(), format!("cid-{}", chunk_id), sequence)
+}
+
+#[test]
+fn recording_the_same_chunk_twice_keeps_one_record() {
+    let mut contract = setup();
+    register(&mut contract, accounts(1));
+
+    assert!(record(&mut contract, accounts(1), "c0", 0));
+    assert!(!record(&mut contract, accounts(1), "c0", 0));
+
+    let records = contract.get_rta_dispatches("rta".to_string());
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].chunk_id, "c0");
+    assert_eq!(contract.get_dispatch_count("rta".to_string()), 1);
+}
+
+#[test]
+#[should_panic(expected = "Worker codehash not approved")]
+fn sign_tx_fails_after_the_codehash_is_revoked() {
+    let mut contract = setup();
+    register(&mut contract, accounts(1));
+    set_caller(accounts(0));
+    contract.remove_codehash(CODEHASH.to_string());
+
+    sign(&mut contract, accounts(1));
+}
+
+#[test]
+fn registration_reports_the_codehash_role() {
+    let mut contract = setup();
+    set_caller(accounts(1));
+    let registration = contract.register_worker_dev(CODEHASH.to_string());
+    assert_eq!(registration.account_id, accounts(1));
+    assert_eq!(registration.codehash, CODEHASH);
+    assert_eq!(registration.role, DEFAULT_WORKER_ROLE);
+
+    set_caller(accounts(0));
+    contract.set_codehash_role(CODEHASH.to_string(), "chunker".to_string());
+    set_caller(accounts(2));
+    assert_eq!(contract.register_worker_dev(CODEHASH.to_string()).role, "chunker");
+}
+
+#[test]
+fn worker_dispatches_page_in_recording_order() {
+    let mut contract = setup();
+    register(&mut contract, accounts(1));
+    register(&mut contract, accounts(2));
+    record(&mut contract, accounts(1), "c2", 2);
+    record(&mut contract, accounts(2), "c1", 1);
+    record(&mut contract, accounts(1), "c0", 0);
+    record(&mut contract, accounts(1), "c3", 3);
+
+    assert_eq!(contract.get_worker_dispatch_count(accounts(1)), 3);
+    let chunk_ids = |records: Vec<DispatchRecord>| records.into_iter().map(|r| r.chunk_id).collect::<Vec<_>>();
+    assert_eq!(chunk_ids(contract.get_worker_dispatches(accounts(1), 0, 2)), ["c2", "c0"]);
+    assert_eq!(chunk_ids(contract.get_worker_dispatches(accounts(1), 2, 2)), ["c3"]);
+    assert_eq!(chunk_ids(contract.get_worker_dispatches(accounts(2), 0, 10)), ["c1"]);
+    assert!(contract.get_worker_dispatches(accounts(3), 0, 10).is_empty());
+}
+
+#[test]
+#[should_panic(expected = "Dev registration is disabled")]
+fn dev_registration_requires_dev_mode() {
+    let mut contract = setup();
+    contract.set_dev_mode(false);
+    set_caller(accounts(1));
+    contract.register_worker_dev(CODEHASH.to_string());
+}
+
+#[test]
+#[should_panic(expected = "Dev registration is disabled")]
+fn dev_worker_is_rejected_once_dev_mode_is_off() {
+    let mut contract = setup();
+    register(&mut contract, accounts(1));
+    set_caller(accounts(0));
+    contract.set_dev_mode(false);
+
+    sign(&mut contract, accounts(1));
+}
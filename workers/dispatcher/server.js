@@ -20,6 +20,7 @@ import multer from 'multer';
 import nearAPI from 'near-api-js';
 const { connect, keyStores, utils } = nearAPI;
 import crypto from 'crypto';
+import http from 'http';
 import { promises as fs } from 'fs';
 import path from 'path';
 import { Synapse, TOKENS, CONTRACT_ADDRESSES, RPC_URLS, PandoraService, SIZE_CONSTANTS, TIME_CONSTANTS } from '@filoz/synapse-sdk';
@@ -76,7 +77,11 @@ class VibesFlowDispatcherWorker {
         };
         
         // TEE Attestation (Development fallbacks)
+        this.isInTEE = Boolean(process.env.DSTACK_SIMULATOR_ENDPOINT || process.env.TEE_MODE === 'production');
+        this.dstackSocket = process.env.DSTACK_SOCKET || '/var/run/dstack.sock';
+        this.teeCollateralUrl = process.env.TEE_COLLATERAL_URL;
         this.teeQuote = 'dev-quote';
+        this.teeTcbInfo = null;
         this.teeChecksum = 'dev-checksum';
         this.teeCollateral = 'dev-collateral';
         
@@ -350,24 +355,16 @@ class VibesFlowDispatcherWorker {
             console.log('🔐 Performing TEE attestation for Shade Agent registration...');
             
             // Check if running in actual TEE environment (Phala Cloud)
-            const isInTEE = process.env.DSTACK_SIMULATOR_ENDPOINT || process.env.TEE_MODE === 'production';
-            
-            if (isInTEE) {
+            if (this.isInTEE) {
                 console.log('🔐 Production TEE environment detected - generating attestation...');
                 
-                try {
-                    // In production TEE, this would call actual attestation APIs
-                    // For now, generate development attestation with TEE-like structure
-                    this.teeQuote = await this.generateTEEQuote();
-                    this.teeChecksum = await this.generateTEEChecksum();
-                    this.teeCollateral = await this.generateTEECollateral();
-                    
-                    console.log(`✅ TEE attestation completed (production mode)`);
-                    
-                } catch (teeError) {
-                    console.warn('⚠️ TEE attestation failed, using development fallback:', teeError.message);
-                    this.generateDevelopmentAttestation();
-                }
+                // No development fallback here: a TEE worker that can't attest must not register
+                this.teeQuote = await this.generateTEEQuote();
+                this.teeTcbInfo = await this.getTEETcbInfo();
+                this.teeChecksum = await this.generateTEEChecksum();
+                this.teeCollateral = await this.generateTEECollateral();
+                
+                console.log(`✅ TEE attestation completed (production mode)`);
                 
             } else {
                 console.log('🔧 Development environment - using development attestation...');
@@ -383,25 +380,57 @@ class VibesFlowDispatcherWorker {
         }
     }
 
-    async generateTEEQuote() {
-        // In production, this would call actual TEE attestation
-        // Following Shade Agents pattern from documentation
-        const timestamp = Date.now();
-        const nonce = crypto.randomBytes(16).toString('hex');
-        
-        return JSON.stringify({
-            version: 3,
-            timestamp: timestamp,
-            nonce: nonce,
-            measurements: {
-                mr_enclave: crypto.randomBytes(32).toString('hex'),
-                mr_signer: crypto.randomBytes(32).toString('hex')
-            },
-            report_data: crypto.randomBytes(64).toString('hex'),
-            signature: crypto.randomBytes(64).toString('hex')
+    // Call the dstack guest agent, over the simulator endpoint when set, else its unix socket
+    dstackRequest(method, body = {}) {
+        const payload = JSON.stringify(body);
+        const endpoint = process.env.DSTACK_SIMULATOR_ENDPOINT;
+        const target = endpoint
+            ? new URL(`/${method}`, endpoint)
+            : { socketPath: this.dstackSocket, path: `/${method}` };
+
+        return new Promise((resolve, reject) => {
+            const req = http.request(target, {
+                method: 'POST',
+                headers: { 'Content-Type': 'application/json', 'Content-Length': Buffer.byteLength(payload) }
+            }, (res) => {
+                let data = '';
+                res.on('data', (chunk) => { data += chunk; });
+                res.on('end', () => {
+                    if (res.statusCode !== 200) {
+                        return reject(new Error(`dstack ${method} failed (${res.statusCode}): ${data}`));
+                    }
+                    try {
+                        resolve(JSON.parse(data));
+                    } catch (error) {
+                        reject(new Error(`dstack ${method} returned invalid JSON`));
+                    }
+                });
+            });
+            req.on('error', reject);
+            req.end(payload);
         });
     }
 
+    async generateTEEQuote() {
+        // The contract requires the quote's report data to start with sha256(worker account id),
+        // so the quote can't be replayed to register another account
+        const reportData = crypto.createHash('sha256').update(this.workerAccountId).digest('hex');
+        const { quote } = await this.dstackRequest('GetQuote', { report_data: reportData });
+        if (!quote) {
+            throw new Error('dstack GetQuote returned no quote');
+        }
+        return quote;
+    }
+
+    async getTEETcbInfo() {
+        // Event log and app compose the contract replays against the quote's RTMRs
+        const { tcb_info: tcbInfo } = await this.dstackRequest('Info');
+        if (!tcbInfo) {
+            throw new Error('dstack Info returned no tcb_info');
+        }
+        return typeof tcbInfo === 'string' ? tcbInfo : JSON.stringify(tcbInfo);
+    }
+
     async generateTEEChecksum() {
         // Generate checksum based on actual code content (like Docker image hash)
         const codeContent = `${this.contractCodehash}:${this.agentContractId}:${Date.now()}`;
@@ -409,12 +438,20 @@ class VibesFlowDispatcherWorker {
     }
 
     async generateTEECollateral() {
-        // TEE collateral would be provided by the TEE environment
-        return JSON.stringify({
-            tcb_info: crypto.randomBytes(32).toString('hex'),
-            qe_identity: crypto.randomBytes(32).toString('hex'),
-            pck_certificate_chain: crypto.randomBytes(64).toString('hex')
+        // Quote collateral in the dcap-qvl QuoteCollateralV3 layout (tcb_info, qe_identity, their
+        // signatures and issuer chains), fetched for this quote's platform
+        if (!this.teeCollateralUrl) {
+            throw new Error('TEE_COLLATERAL_URL is not configured');
+        }
+        const response = await fetch(this.teeCollateralUrl, {
+            method: 'POST',
+            headers: { 'Content-Type': 'application/json' },
+            body: JSON.stringify({ quote: this.teeQuote })
         });
+        if (!response.ok) {
+            throw new Error(`Collateral fetch failed: ${response.status}`);
+        }
+        return JSON.stringify(await response.json());
     }
 
     generateDevelopmentAttestation() {
@@ -473,43 +510,34 @@ class VibesFlowDispatcherWorker {
                 }
             }
             
-            // Try production TEE registration first (proper Shade Agents flow)
-            if (this.teeQuote.includes('production') || process.env.TEE_MODE === 'production') {
+            // Production TEE registration (proper Shade Agents flow). A failure here is final:
+            // falling back to register_worker_dev would register the worker without attestation
+            if (this.isInTEE) {
                 console.log('📝 Attempting production TEE worker registration...');
                 
-                try {
-                    const registrationArgs = {
-                        verified_codehash: this.contractCodehash,
-                        worker_account_id: this.workerAccountId,
-                        checksum: this.teeChecksum,
-                        tee_verification_proof: JSON.stringify({
-                            quote: this.teeQuote,
-                            collateral: this.teeCollateral,
-                            checksum: this.teeChecksum,
-                            verified_at: Date.now(),
-                            verification_method: 'dcap_qvl'
-                        })
-                    };
-                    
-                    console.log('📝 Calling register_worker with TEE attestation...');
-                    const result = await this.workerAccount.functionCall(
-                        this.agentContractId,
-                        'register_worker',
-                        registrationArgs,
-                        '300000000000000', // 300 TGas
-                        '0' // No deposit
-                    );
-                    
-                    console.log(`✅ Worker registered with production TEE attestation`);
-                    console.log(`🔐 TEE Checksum: ${this.teeChecksum}`);
-                    
-                } catch (teeError) {
-                    console.error('❌ Production TEE registration failed:', teeError);
-                    console.log('⚠️ Falling back to development registration...');
-                    
-                    // Fall back to development registration
-                    await this.registerWorkerDev();
-                }
+                const registrationArgs = {
+                    verified_codehash: this.contractCodehash,
+                    worker_account_id: this.workerAccountId,
+                    checksum: this.teeChecksum,
+                    // TeeVerificationProof: collateral JSON, hex quote, dstack tcb_info JSON
+                    tee_verification_proof: JSON.stringify({
+                        collateral: this.teeCollateral,
+                        quote: this.teeQuote,
+                        tcb_info: this.teeTcbInfo
+                    })
+                };
+                
+                console.log('📝 Calling register_worker with TEE attestation...');
+                await this.workerAccount.functionCall(
+                    this.agentContractId,
+                    'register_worker',
+                    registrationArgs,
+                    '300000000000000', // 300 TGas
+                    '0' // No deposit
+                );
+                
+                console.log(`✅ Worker registered with production TEE attestation`);
+                console.log(`🔐 TEE Checksum: ${this.teeChecksum}`);
                 
             } else {
                 // Use development registration for development environment
@@ -524,6 +552,9 @@ class VibesFlowDispatcherWorker {
                     'get_worker',
                     { account_id: this.workerAccountId }
                 );
+                if (!worker) {
+                    throw new Error('Worker not found after registration');
+                }
                 
                 console.log(`✅ Worker registration verified:`, worker);
                 this.isRegistered = true;
@@ -539,6 +570,7 @@ class VibesFlowDispatcherWorker {
         }
     }
 
+    // Only accepted while the contract owner has called set_dev_mode(true)
     async registerWorkerDev() {
         try {
             console.log('📝 Registering in development mode...');
@@ -560,6 +592,17 @@ class VibesFlowDispatcherWorker {
         }
     }
 
+    // Chunk index used as the on-chain dispatch sequence. Taken from the chunk
+    // metadata, or from the `_chunk_NNN_` segment of the chunk ID.
+    getChunkIndex(chunkId, metadata) {
+        const index = Number(metadata?.chunk_index);
+        if (Number.isSafeInteger(index) && index >= 0) {
+            return index;
+        }
+        const match = typeof chunkId === 'string' && chunkId.match(/_chunk_(\d+)(?:_|$)/);
+        return match ? parseInt(match[1], 10) : null;
+    }
+
     setupExpress() {
         // CORS Configuration
         this.app.use(cors({
@@ -645,6 +688,11 @@ class VibesFlowDispatcherWorker {
                     return res.status(503).json({ error: 'Storage service not available' });
                 }
 
+                const chunkIndex = this.getChunkIndex(req.body.chunkId, { chunk_index: req.body.chunkIndex });
+                if (chunkIndex === null) {
+                    return res.status(400).json({ error: 'chunkIndex is required' });
+                }
+
                 console.log(`📤 Processing file upload: ${req.file.originalname} (${(req.file.size / 1024 / 1024).toFixed(2)}MB)`);
 
                 // Create metadata
@@ -681,7 +729,7 @@ class VibesFlowDispatcherWorker {
 
                 // Record dispatch in contract
                 try {
-                    const chunkId = crypto.randomBytes(16).toString('hex');
+                    const chunkId = req.body.chunkId || crypto.randomBytes(16).toString('hex');
                     const rtaId = req.body.rtaId || 'default-rta';
                     
                     if (this.workerAccount && this.isRegistered) {
@@ -691,7 +739,8 @@ class VibesFlowDispatcherWorker {
                             {
                                 rta_id: rtaId,
                                 chunk_id: chunkId,
-                                filecoin_cid: commp
+                                filecoin_cid: commp,
+                                sequence: chunkIndex
                             },
                             '100000000000000', // 100 TGas
                             '0' // No deposit
@@ -731,6 +780,11 @@ class VibesFlowDispatcherWorker {
                     return res.status(503).json({ error: 'Storage service not available' });
                 }
 
+                const chunkIndex = this.getChunkIndex(chunkId, metadata);
+                if (chunkIndex === null) {
+                    return res.status(400).json({ error: 'Chunk index missing from metadata and chunkId' });
+                }
+
                 console.log(`📦 Uploading chunk: ${chunkId}`);
                 console.log(`🏆 Chunk owner: ${metadata.chunk_owner}`);
                 console.log(`🎲 VRF proof verified: ${metadata.raffle_proof ? 'YES' : 'NO'}`);
@@ -815,7 +869,8 @@ class VibesFlowDispatcherWorker {
                                     rta_id: rtaId,
                                     chunk_id: chunkId,
                                     filecoin_cid: commp,
-                                    chunk_owner: metadata.chunk_owner
+                                    chunk_owner: metadata.chunk_owner,
+                                    sequence: chunkIndex
                                 },
                                 '100000000000000', // 100 TGas
                                 '0' // No deposit